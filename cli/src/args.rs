@@ -0,0 +1,348 @@
+//! Command-line arguments for the simulator.
+
+use {
+    clap::{Parser, Subcommand, ValueEnum},
+    p_token_migration_harness::{
+        activation,
+        report::{self, Thresholds},
+        validator::{LedgerOrigin, MigrationTarget, ValidatorContext, LEDGER_PATH},
+        workload::{InstructionKind, InstructionMix},
+    },
+    std::{io::Result, net::SocketAddr, path::PathBuf},
+};
+
+/// Default directory to look for program ELF files.
+pub const ELF_DIRECTORY: &str = "./target/elfs";
+
+/// Default directory of the ledgers used by the `diff` command.
+pub const DIFF_LEDGER_PATH: &str = "./target/diff-ledgers";
+
+/// Default path of the ledger used by the `snapshot` command.
+pub const SNAPSHOT_LEDGER_PATH: &str = "./target/snapshot-ledger";
+
+#[derive(Parser)]
+#[command(name = "simulate", version, about = "p-token migration simulator")]
+pub struct Args {
+    /// Number of worker threads of the tokio runtime.
+    #[arg(long, global = true, default_value_t = 4)]
+    pub worker_threads: usize,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start a validator with SPL Token, send transactions and activate
+    /// the p-token feature.
+    Run(RunArgs),
+
+    /// Run a migration scenario described in a TOML or JSON file.
+    Scenario(ScenarioArgs),
+
+    /// Execute the same transactions on SPL Token and p-token and report
+    /// any difference in their outcome.
+    Diff(DiffArgs),
+
+    /// Compare the compute units consumed by each token instruction before
+    /// and after the migration.
+    Bench(BenchArgs),
+
+    /// Create the client accounts on a new validator and save a snapshot
+    /// of its state before the feature activation.
+    Snapshot(SnapshotArgs),
+
+    /// Inspect the features of the migration targets.
+    #[command(subcommand)]
+    Feature(FeatureCommand),
+}
+
+#[derive(Subcommand)]
+pub enum FeatureCommand {
+    /// Report the feature account and runtime status of each migration
+    /// target on the ledger.
+    Status(FeatureStatusArgs),
+}
+
+/// Arguments to configure the test validator.
+#[derive(clap::Args)]
+pub struct ValidatorArgs {
+    /// Directory containing the program ELF files.
+    #[arg(long, default_value = ELF_DIRECTORY)]
+    pub elf_directory: String,
+
+    /// Path of the validator ledger; an existing ledger is reused.
+    #[arg(long, default_value = LEDGER_PATH)]
+    pub ledger_path: PathBuf,
+
+    /// Directory of a snapshot created by the `snapshot` command to start
+    /// the validator from; the ledger is restored inside the directory.
+    #[arg(long, conflicts_with = "ledger_path")]
+    pub from_snapshot: Option<PathBuf>,
+
+    /// Number of slots per epoch.
+    #[arg(long, default_value_t = 50)]
+    pub slots_per_epoch: u64,
+
+    /// Account dumps written by `solana account --output json`, as files or
+    /// directories of `.json` files, added to new ledgers; can be repeated.
+    #[arg(
+        long = "accounts",
+        value_name = "PATH",
+        conflicts_with = "from_snapshot"
+    )]
+    pub account_dumps: Vec<PathBuf>,
+}
+
+impl ValidatorArgs {
+    /// Starts the test validator with the migration targets staged.
+    pub async fn start(
+        &self,
+        migration_targets: &[MigrationTarget<'_>],
+    ) -> Result<(ValidatorContext, LedgerOrigin)> {
+        ValidatorContext::start_or_restore(
+            migration_targets,
+            &self.elf_directory,
+            &self.ledger_path,
+            self.slots_per_epoch,
+            &self.account_dumps,
+            self.from_snapshot.as_deref(),
+        )
+        .await
+    }
+}
+
+#[derive(clap::Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub validator: ValidatorArgs,
+
+    /// Number of clients sending transactions.
+    #[arg(long, default_value_t = 25)]
+    pub clients: u64,
+
+    /// Weighted mix of token instructions sent by the clients, e.g.
+    /// `transfer=10,approve=1,burn_checked=2`.
+    #[arg(long, default_value = "transfer")]
+    pub mix: InstructionMix,
+
+    /// Target number of transactions per second sent by all the clients,
+    /// without waiting for confirmations; invariants are not checked.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub tps: Option<u64>,
+
+    /// Maximum number of transactions waiting for confirmation when sending
+    /// at a target TPS; sends are skipped while the limit is reached.
+    #[arg(
+        long,
+        default_value_t = 1000,
+        requires = "tps",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_in_flight: usize,
+
+    /// Delay (in seconds) before activating the feature; the feature can
+    /// also be activated from the dashboard.
+    #[arg(long, default_value_t = 10)]
+    pub activation_delay: u64,
+
+    /// File with the `[[targets]]` to stage, in the scenario format; only
+    /// p-token is staged by default.
+    #[arg(long)]
+    pub targets: Option<PathBuf>,
+
+    /// Order in which the features of the targets are activated.
+    #[arg(long, value_enum, default_value_t = ActivationOrder::Sequential)]
+    pub activation_order: ActivationOrder,
+
+    /// Print progress lines instead of showing the dashboard.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Address to serve Prometheus metrics on, at `/metrics`, e.g.
+    /// `127.0.0.1:9090`.
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Number of slots to run after the feature activation; without it, the
+    /// simulation runs until CTRL+C is pressed.
+    #[arg(long)]
+    pub run_slots: Option<u64>,
+
+    #[command(flatten)]
+    pub thresholds: ThresholdArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ActivationOrder {
+    /// One target per epoch, in the order of the targets file.
+    Sequential,
+    /// All targets in the same epoch.
+    Together,
+}
+
+impl From<ActivationOrder> for activation::ActivationOrder {
+    fn from(order: ActivationOrder) -> Self {
+        match order {
+            ActivationOrder::Sequential => Self::Sequential,
+            ActivationOrder::Together => Self::Together,
+        }
+    }
+}
+
+/// Thresholds checked at the end of a bounded run.
+#[derive(clap::Args)]
+pub struct ThresholdArgs {
+    /// Maximum number of consecutive failed transactions of a client.
+    #[arg(long, requires = "run_slots")]
+    pub max_consecutive_failures: Option<u64>,
+
+    /// Maximum number of slots with failed transactions after activation.
+    #[arg(long, requires = "run_slots")]
+    pub max_outage_slots: Option<u64>,
+
+    /// Minimum success rate (in percent) of transactions after the upgrade.
+    #[arg(long, requires = "run_slots")]
+    pub min_success_rate: Option<f64>,
+}
+
+impl From<&ThresholdArgs> for Thresholds {
+    fn from(args: &ThresholdArgs) -> Self {
+        Self {
+            max_consecutive_failures: args.max_consecutive_failures,
+            max_outage_slots: args.max_outage_slots,
+            min_success_rate: args.min_success_rate,
+        }
+    }
+}
+
+/// Arguments to write a report of every transaction at the end of the run.
+#[derive(clap::Args)]
+pub struct ReportArgs {
+    /// Path of the transaction report file.
+    #[arg(long = "report")]
+    pub path: Option<PathBuf>,
+
+    /// Format of the transaction report.
+    #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Jsonl)]
+    pub format: ReportFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    /// JSON Lines, one object per transaction.
+    Jsonl,
+    /// CSV with a header row.
+    Csv,
+}
+
+impl From<ReportFormat> for report::ReportFormat {
+    fn from(format: ReportFormat) -> Self {
+        match format {
+            ReportFormat::Jsonl => Self::JsonLines,
+            ReportFormat::Csv => Self::Csv,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct ScenarioArgs {
+    #[command(flatten)]
+    pub validator: ValidatorArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+
+    /// Address to serve Prometheus metrics on, at `/metrics`, e.g.
+    /// `127.0.0.1:9090`.
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Path of the scenario file.
+    pub path: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct DiffArgs {
+    /// Directory containing the program ELF files.
+    #[arg(long, default_value = ELF_DIRECTORY)]
+    pub elf_directory: String,
+
+    /// Directory of the validator ledgers; existing ledgers are replaced.
+    #[arg(long, default_value = DIFF_LEDGER_PATH)]
+    pub ledger_path: PathBuf,
+
+    /// Number of slots per epoch.
+    #[arg(long, default_value_t = 50)]
+    pub slots_per_epoch: u64,
+
+    /// Comma-separated instructions to compare, e.g. `transfer,approve`.
+    /// All instructions are compared by default.
+    #[arg(long, value_delimiter = ',')]
+    pub mix: Vec<InstructionKind>,
+
+    /// Number of transactions sent for each instruction.
+    #[arg(long, default_value_t = 5)]
+    pub iterations: u64,
+
+    /// Do not report differences in the program logs.
+    #[arg(long)]
+    pub ignore_logs: bool,
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub validator: ValidatorArgs,
+
+    /// Comma-separated instructions to measure, e.g. `transfer,approve`.
+    /// All instructions are measured by default.
+    #[arg(long, value_delimiter = ',')]
+    pub mix: Vec<InstructionKind>,
+
+    /// Path of a CSV file to write the results to.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct FeatureStatusArgs {
+    /// Path of the validator ledger to inspect; it is not modified.
+    #[arg(long, default_value = LEDGER_PATH)]
+    pub ledger_path: PathBuf,
+
+    /// File with the `[[targets]]` to inspect, in the scenario format; only
+    /// p-token is inspected by default.
+    #[arg(long)]
+    pub targets: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct SnapshotArgs {
+    /// Directory containing the program ELF files.
+    #[arg(long, default_value = ELF_DIRECTORY)]
+    pub elf_directory: String,
+
+    /// Path of the validator ledger; an existing ledger is replaced.
+    #[arg(long, default_value = SNAPSHOT_LEDGER_PATH)]
+    pub ledger_path: PathBuf,
+
+    /// Number of slots per epoch.
+    #[arg(long, default_value_t = 50)]
+    pub slots_per_epoch: u64,
+
+    /// Account dumps written by `solana account --output json`, as files or
+    /// directories of `.json` files; can be repeated.
+    #[arg(long = "accounts", value_name = "PATH")]
+    pub account_dumps: Vec<PathBuf>,
+
+    /// Number of client fixtures to create.
+    #[arg(long, default_value_t = 25)]
+    pub clients: u64,
+
+    /// Directory to write the snapshot to.
+    pub output: PathBuf,
+}
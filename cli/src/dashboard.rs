@@ -0,0 +1,548 @@
+//! Full-screen dashboard of the simulation.
+
+use {
+    p_token_migration_harness::{
+        client::ClientRegistry, validator::FeatureInfo, workload::InstructionKind,
+    },
+    ratatui::{
+        crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        layout::{Constraint, Layout, Rect},
+        style::{Color, Style},
+        symbols::Marker,
+        text::{Line, Span},
+        widgets::{Axis, Block, Chart, Dataset, Gauge, GraphType, Paragraph, Sparkline},
+        Frame,
+    },
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_runtime::bank_forks::BankForks,
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::{BTreeMap, VecDeque},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex, RwLock,
+        },
+        time::{Duration, Instant},
+    },
+    tokio::{
+        sync::Notify,
+        time::{interval, sleep},
+    },
+    tokio_util::sync::CancellationToken,
+};
+
+/// Number of samples kept for the charts.
+const HISTORY_LENGTH: usize = 120;
+
+/// Interval between samples of the client counters.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval between redraws of the dashboard.
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Interval between progress lines when running without the dashboard.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Live state of a run, shared between the simulation and the dashboard.
+pub struct RunState {
+    pub clients: ClientRegistry,
+    /// Compute units of the last simulation of each instruction.
+    pub units: Arc<Mutex<BTreeMap<InstructionKind, u64>>>,
+    pub slot: AtomicU64,
+    pub epoch: AtomicU64,
+    pub program_owner: Mutex<Option<Pubkey>>,
+    /// Feature of each migration target, by target name.
+    pub features: Mutex<Vec<(String, FeatureInfo)>>,
+}
+
+impl RunState {
+    pub fn new(clients: ClientRegistry) -> Self {
+        Self {
+            clients,
+            units: Arc::default(),
+            slot: AtomicU64::default(),
+            epoch: AtomicU64::default(),
+            program_owner: Mutex::default(),
+            features: Mutex::default(),
+        }
+    }
+
+    // Returns the total number of successful and failed transactions.
+    fn totals(&self) -> (u64, u64) {
+        self.clients
+            .clients()
+            .iter()
+            .fold((0, 0), |(success, errors), client| {
+                (success + client.success(), errors + client.total_errors())
+            })
+    }
+
+    // Returns the number of transactions in flight and of dropped sends.
+    fn backlog(&self) -> (u64, u64) {
+        self.clients
+            .clients()
+            .iter()
+            .fold((0, 0), |(in_flight, dropped), client| {
+                (in_flight + client.in_flight(), dropped + client.dropped())
+            })
+    }
+
+    fn features_label(&self) -> String {
+        let features = self.features.lock().unwrap();
+
+        if features.is_empty() {
+            return "-".to_string();
+        }
+
+        features
+            .iter()
+            .map(|(name, feature)| format!("{name} [{feature}]"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn units_label(&self) -> String {
+        let units = self.units.lock().unwrap();
+
+        if units.is_empty() {
+            return "-".to_string();
+        }
+
+        units
+            .iter()
+            .map(|(kind, units)| format!("{kind} {units}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn owner_label(&self) -> String {
+        match *self.program_owner.lock().unwrap() {
+            Some(owner) if owner == solana_sdk_ids::bpf_loader::id() => "loader v2".to_string(),
+            Some(owner) if owner == solana_sdk_ids::bpf_loader_upgradeable::id() => {
+                "upgradeable".to_string()
+            }
+            Some(owner) => owner.to_string(),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Polls the validator for the current slot, epoch, owner of the program
+/// and the features of the migration targets, by target name.
+pub async fn poll_cluster(
+    state: Arc<RunState>,
+    rpc_client: RpcClient,
+    bank_forks: Arc<RwLock<BankForks>>,
+    program_id: Pubkey,
+    features: Vec<(String, Pubkey)>,
+    interrupted: CancellationToken,
+) {
+    loop {
+        if let Ok(epoch_info) = rpc_client.get_epoch_info().await {
+            state
+                .slot
+                .store(epoch_info.absolute_slot, Ordering::Relaxed);
+            state.epoch.store(epoch_info.epoch, Ordering::Relaxed);
+        }
+
+        if let Ok(account) = rpc_client.get_account(&program_id).await {
+            *state.program_owner.lock().unwrap() = Some(account.owner);
+        }
+
+        {
+            let bank = bank_forks.read().unwrap().working_bank();
+            *state.features.lock().unwrap() = features
+                .iter()
+                .map(|(name, feature_id)| (name.clone(), FeatureInfo::read(&bank, feature_id)))
+                .collect();
+        }
+
+        if interrupted
+            .run_until_cancelled(sleep(Duration::from_millis(400)))
+            .await
+            .is_none()
+        {
+            break;
+        }
+    }
+}
+
+/// Prints a progress line periodically, for runs without the dashboard.
+pub async fn print_progress(state: Arc<RunState>, interrupted: CancellationToken) {
+    let mut ticker = interval(PROGRESS_INTERVAL);
+    // The first tick completes right away.
+    ticker.tick().await;
+
+    while interrupted
+        .run_until_cancelled(ticker.tick())
+        .await
+        .is_some()
+    {
+        let (success, errors) = state.totals();
+        let (in_flight, dropped) = state.backlog();
+
+        println!(
+            "[slot {} | epoch {}] ✅ {success} ❌ {errors} | in flight: {in_flight} | \
+             dropped: {dropped} | CUs: {} | owner: {} | features: {}",
+            state.slot.load(Ordering::Relaxed),
+            state.epoch.load(Ordering::Relaxed),
+            state.units_label(),
+            state.owner_label(),
+            state.features_label(),
+        );
+    }
+}
+
+/// Runs the dashboard until `interrupted` is cancelled.
+///
+/// Pressing `a` notifies `activate`, while `q`, `Esc` or `CTRL+C` stop the
+/// run. This function blocks, so it should run on a dedicated thread.
+pub fn run(state: Arc<RunState>, activate: Arc<Notify>, interrupted: CancellationToken) {
+    let mut terminal = ratatui::init();
+    let mut dashboard = Dashboard::new(state);
+
+    while !interrupted.is_cancelled() {
+        dashboard.sample();
+
+        if terminal.draw(|frame| dashboard.render(frame)).is_err() {
+            break;
+        }
+
+        if !event::poll(TICK_RATE).unwrap_or(false) {
+            continue;
+        }
+
+        if let Ok(Event::Key(key)) = event::read() {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('a') => activate.notify_one(),
+                KeyCode::Char('q') | KeyCode::Esc => interrupted.cancel(),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    interrupted.cancel()
+                }
+                _ => (),
+            }
+        }
+    }
+
+    ratatui::restore();
+}
+
+/// History of a client counters.
+#[derive(Default)]
+struct ClientHistory {
+    last_success: u64,
+    last_errors: u64,
+    success: VecDeque<u64>,
+    errors: VecDeque<u64>,
+}
+
+struct Dashboard {
+    state: Arc<RunState>,
+    started: Instant,
+    last_sample: Instant,
+    /// Number of samples taken so far.
+    samples: u64,
+    clients: Vec<ClientHistory>,
+    /// Total number of successful and failed transactions per sample.
+    throughput: VecDeque<(u64, u64)>,
+    /// Maximum number of compute units observed for each instruction.
+    max_units: BTreeMap<InstructionKind, u64>,
+}
+
+impl Dashboard {
+    fn new(state: Arc<RunState>) -> Self {
+        let clients = state
+            .clients
+            .clients()
+            .iter()
+            .map(|_| ClientHistory::default())
+            .collect();
+
+        Self {
+            state,
+            started: Instant::now(),
+            last_sample: Instant::now(),
+            samples: 0,
+            clients,
+            throughput: VecDeque::with_capacity(HISTORY_LENGTH),
+            max_units: BTreeMap::new(),
+        }
+    }
+
+    // Records the number of transactions since the last sample.
+    fn sample(&mut self) {
+        if self.last_sample.elapsed() < SAMPLE_INTERVAL {
+            return;
+        }
+        self.last_sample = Instant::now();
+        self.samples += 1;
+
+        let mut total = (0, 0);
+
+        for (stats, history) in self
+            .state
+            .clients
+            .clients()
+            .iter()
+            .zip(self.clients.iter_mut())
+        {
+            let (success, errors) = (stats.success(), stats.total_errors());
+
+            push_sample(&mut history.success, success - history.last_success);
+            push_sample(&mut history.errors, errors - history.last_errors);
+
+            total.0 += success - history.last_success;
+            total.1 += errors - history.last_errors;
+
+            history.last_success = success;
+            history.last_errors = errors;
+        }
+
+        if self.throughput.len() == HISTORY_LENGTH {
+            self.throughput.pop_front();
+        }
+        self.throughput.push_back(total);
+
+        for (kind, units) in self.state.units.lock().unwrap().iter() {
+            let max = self.max_units.entry(*kind).or_default();
+            *max = (*max).max(*units);
+        }
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let features = self.state.features.lock().unwrap().len() as u16;
+
+        let rows = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(features + 2),
+            Constraint::Length(12),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+
+        self.render_header(frame, rows[0]);
+        self.render_features(frame, rows[1]);
+
+        let charts = Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(rows[2]);
+
+        self.render_throughput(frame, charts[0]);
+        self.render_units(frame, charts[1]);
+        self.render_clients(frame, rows[3]);
+
+        frame.render_widget(
+            Paragraph::new(" a: activate feature | q: stop the run"),
+            rows[4],
+        );
+    }
+
+    fn render_features(&self, frame: &mut Frame, area: Rect) {
+        let label = Style::default().fg(Color::DarkGray);
+
+        let lines = self
+            .state
+            .features
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, feature)| {
+                Line::from(vec![
+                    Span::raw(format!("{name} ")),
+                    Span::styled(feature.feature_id.to_string(), label),
+                    Span::raw(format!(" {feature}")),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Features ")),
+            area,
+        );
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let elapsed = self.started.elapsed().as_secs();
+        let label = Style::default().fg(Color::DarkGray);
+        let (in_flight, dropped) = self.state.backlog();
+
+        let line = Line::from(vec![
+            Span::styled("slot ", label),
+            Span::raw(self.state.slot.load(Ordering::Relaxed).to_string()),
+            Span::styled(" | epoch ", label),
+            Span::raw(self.state.epoch.load(Ordering::Relaxed).to_string()),
+            Span::styled(" | owner ", label),
+            Span::raw(self.state.owner_label()),
+            Span::styled(" | in flight ", label),
+            Span::raw(in_flight.to_string()),
+            Span::styled(" | dropped ", label),
+            Span::raw(dropped.to_string()),
+            Span::styled(" | elapsed ", label),
+            Span::raw(format!("{:02}:{:02}", elapsed / 60, elapsed % 60)),
+        ]);
+
+        frame.render_widget(
+            Paragraph::new(line).block(Block::bordered().title(" p-token migration simulator ")),
+            area,
+        );
+    }
+
+    fn render_throughput(&self, frame: &mut Frame, area: Rect) {
+        let start = self.samples.saturating_sub(self.throughput.len() as u64);
+
+        let success = self
+            .throughput
+            .iter()
+            .enumerate()
+            .map(|(i, (success, _))| ((start + i as u64) as f64, *success as f64))
+            .collect::<Vec<_>>();
+        let errors = self
+            .throughput
+            .iter()
+            .enumerate()
+            .map(|(i, (_, errors))| ((start + i as u64) as f64, *errors as f64))
+            .collect::<Vec<_>>();
+
+        let max = self
+            .throughput
+            .iter()
+            .map(|(success, errors)| *success.max(errors))
+            .max()
+            .unwrap_or_default()
+            .max(1);
+
+        let datasets = vec![
+            Dataset::default()
+                .name("success")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Green))
+                .data(&success),
+            Dataset::default()
+                .name("errors")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Red))
+                .data(&errors),
+        ];
+
+        let end = start + HISTORY_LENGTH as u64;
+
+        let chart = Chart::new(datasets)
+            .block(Block::bordered().title(" Throughput (tx/s) "))
+            .x_axis(Axis::default().bounds([start as f64, end as f64]))
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, max as f64])
+                    .labels(vec![Span::raw("0"), Span::raw(max.to_string())]),
+            );
+
+        frame.render_widget(chart, area);
+    }
+
+    fn render_units(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Compute units ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let units = self.state.units.lock().unwrap();
+
+        for (i, (kind, units)) in units.iter().take(inner.height as usize).enumerate() {
+            let row = Rect {
+                x: inner.x,
+                y: inner.y + i as u16,
+                width: inner.width,
+                height: 1,
+            };
+            let max = self.max_units.get(kind).copied().unwrap_or(*units).max(1);
+
+            let columns =
+                Layout::horizontal([Constraint::Length(20), Constraint::Min(0)]).split(row);
+
+            frame.render_widget(Paragraph::new(kind.name()), columns[0]);
+            frame.render_widget(
+                Gauge::default()
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .ratio((*units as f64 / max as f64).min(1.0))
+                    .label(format!("{units} (max {max})")),
+                columns[1],
+            );
+        }
+    }
+
+    fn render_clients(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Clients (success | errors per second) ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        for (i, (stats, history)) in self
+            .state
+            .clients
+            .clients()
+            .iter()
+            .zip(self.clients.iter())
+            .take(inner.height as usize)
+            .enumerate()
+        {
+            let row = Rect {
+                x: inner.x,
+                y: inner.y + i as u16,
+                width: inner.width,
+                height: 1,
+            };
+
+            let columns = Layout::horizontal([
+                Constraint::Length(28),
+                Constraint::Percentage(70),
+                Constraint::Percentage(30),
+            ])
+            .split(row);
+
+            frame.render_widget(
+                Paragraph::new(format!(
+                    "client #{:02} ✅ {} ❌ {}",
+                    stats.id,
+                    stats.success(),
+                    stats.total_errors()
+                )),
+                columns[0],
+            );
+
+            let success = last_samples(&history.success, columns[1].width);
+            frame.render_widget(
+                Sparkline::default()
+                    .data(&success)
+                    .style(Style::default().fg(Color::Green)),
+                columns[1],
+            );
+
+            let errors = last_samples(&history.errors, columns[2].width);
+            frame.render_widget(
+                Sparkline::default()
+                    .data(&errors)
+                    .style(Style::default().fg(Color::Red)),
+                columns[2],
+            );
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<u64>, value: u64) {
+    if samples.len() == HISTORY_LENGTH {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+// Returns the most recent samples that fit in the given width.
+fn last_samples(samples: &VecDeque<u64>, width: u16) -> Vec<u64> {
+    samples
+        .iter()
+        .skip(samples.len().saturating_sub(width as usize))
+        .copied()
+        .collect()
+}
@@ -1,162 +1,773 @@
-mod client;
-mod file;
-mod validator;
+mod args;
+mod dashboard;
+mod metrics;
 
 use std::{
-    io::Result,
-    path::PathBuf,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    thread::sleep,
+    fs::File,
+    io::{BufWriter, Result},
+    process::ExitCode,
+    sync::Arc,
     time::Duration,
 };
 
-use agave_feature_set::replace_spl_token_with_p_token::{
-    ID, PTOKEN_PROGRAM_BUFFER, SPL_TOKEN_PROGRAM_ID,
+use agave_feature_set::replace_spl_token_with_p_token::SPL_TOKEN_PROGRAM_ID;
+use clap::Parser;
+use p_token_migration_harness::{
+    activate_targets,
+    bench::{BenchFixtures, BenchReport, Variant},
+    client::{start_monitor, ClientRegistry, OpenLoop, Workload},
+    diff::DiffContext,
+    invariant::Invariants,
+    report::{finish_run, MigrationTimeline, Recorder, RunSummary, Thresholds},
+    scenario::{self, Scenario, TargetConfig},
+    snapshot,
+    validator::{
+        read_ledger_features, LedgerOrigin, MigrationTarget, ValidatorContext, P_TOKEN_MIGRATION,
+    },
+    verify::verify_targets,
+    workload::{Fixture, FixtureState, InstructionKind, Operation},
+    ActivationOrder,
 };
-use indicatif::{MultiProgress, ProgressBar};
+use solana_rpc_client_api::config::CommitmentConfig;
 use solana_sdk::signature::Keypair;
 use solana_sdk_ids::bpf_loader_upgradeable;
-use tokio::spawn;
+use tokio::{spawn, sync::Notify, task::spawn_blocking};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    client::{start_client, start_monitor},
-    validator::{MigrationTarget, ValidatorContext, LEDGER_PATH},
+    args::{
+        Args, BenchArgs, Command, DiffArgs, FeatureCommand, FeatureStatusArgs, ReportArgs, RunArgs,
+        ScenarioArgs, SnapshotArgs, ValidatorArgs,
+    },
+    dashboard::{poll_cluster, print_progress, RunState},
 };
 
-const ELF_DIRECTORY: &str = "./target/elfs";
-
-const CLIENT_THREADS: u64 = 25;
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(args.worker_threads)
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            match args.command {
+                Command::Run(args) => run(args).await,
+                Command::Scenario(args) => run_scenario(args).await,
+                Command::Diff(args) => run_diff(args).await,
+                Command::Bench(args) => run_bench(args).await,
+                Command::Snapshot(args) => run_snapshot(args).await,
+                Command::Feature(FeatureCommand::Status(args)) => run_feature_status(args).await,
+            }
+        })
+}
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 60)]
-async fn main() -> Result<()> {
-    // Handle CTRL+C.
-    let interrupted = Arc::new(AtomicBool::new(false));
+// Handle CTRL+C: the first press cancels the returned token, the second
+// one exits right away.
+fn interrupt_handler() -> CancellationToken {
+    let interrupted = CancellationToken::new();
     let ctrl_handler = interrupted.clone();
 
     ctrlc::set_handler(move || {
-        if ctrl_handler.load(Ordering::SeqCst) {
+        if ctrl_handler.is_cancelled() {
             // We really need to exit.
             println!("\n\n🟥 Simulation aborted.");
             std::process::exit(0);
         }
         // Signal that we want to exit.
-        ctrl_handler.store(true, Ordering::SeqCst);
+        ctrl_handler.cancel();
     })
     .expect("Error setting Ctrl-C handler");
 
+    interrupted
+}
+
+async fn run(args: RunArgs) -> Result<ExitCode> {
+    let interrupted = interrupt_handler();
+
     println!("p-token migration simulator");
     println!("---------------------------");
 
-    // 1) Start a test validator with the original SPL Token.
+    // 1) Start a test validator with the original programs.
+
+    let targets = match &args.targets {
+        Some(path) => Scenario::load(path)?.targets,
+        None => scenario::default_targets(),
+    };
 
-    let existing = PathBuf::from(LEDGER_PATH).exists();
+    let migration_targets = targets
+        .iter()
+        .map(TargetConfig::as_migration_target)
+        .collect::<Vec<_>>();
 
     println!("\n⚙️  Starting test validator\n",);
 
-    let context = ValidatorContext::start(
-        &[MigrationTarget {
-            feature_id: ID,
-            buffer_address: PTOKEN_PROGRAM_BUFFER,
-            elf_name: "p_token",
-        }],
-        ELF_DIRECTORY,
-        50, // <- slots_per_epoch
-    )
-    .await;
-
-    if existing {
-        println!("  + 🗂️ Existing ledger found: {LEDGER_PATH}");
-    }
+    let (context, origin) = args.validator.start(&migration_targets).await?;
+    print_ledger_origin(&origin, &args.validator);
 
     println!("...done ✅");
 
-    // 2) Assert whether SPL Token is a Loader v2 program or not.
+    // 2) Check which programs are not upgraded yet.
 
-    println!("\n🔍 Check SPL Token program ownership\n",);
+    println!("\n🔍 Check program ownership\n",);
 
-    let (owner, upgraded) = if existing {
-        let account = context
-            .test_validator
-            .get_rpc_client()
-            .get_account(&SPL_TOKEN_PROGRAM_ID)
-            .unwrap();
-        (account.owner, account.owner == bpf_loader_upgradeable::id())
-    } else {
-        context
-            .assert_owner(&SPL_TOKEN_PROGRAM_ID, &solana_sdk::bpf_loader::id())
-            .await;
+    let mut pending = Vec::new();
 
-        (solana_sdk::bpf_loader::id(), false)
-    };
+    for target in &migration_targets {
+        let owner = context
+            .get_account(&target.program_id)
+            .await
+            .map(|account| account.owner);
+
+        // Programs of a new ledger must not be upgraded yet.
+        if matches!(origin, LedgerOrigin::New) {
+            assert!(
+                owner != Some(bpf_loader_upgradeable::id()),
+                "program of '{}' already upgraded",
+                target.name
+            );
+        }
+
+        println!("Program: {} ({})", target.program_id, target.name);
+        println!(
+            "Owner: {}",
+            owner.map_or("-".to_string(), |owner| owner.to_string())
+        );
 
-    println!("Program: {}", SPL_TOKEN_PROGRAM_ID);
-    println!("Owner: {}", owner);
+        if owner != Some(bpf_loader_upgradeable::id()) {
+            pending.push(*target);
+        }
+    }
+
+    let upgraded = pending.is_empty();
 
     println!("\n...done ✅",);
 
     if upgraded {
         println!("\n[⏳ Upgraded, sending transactions; CTRL+C to abort]\n");
     } else {
-        println!("\n[⏳ Activating feature in 10 seconds; CTRL+C to abort]\n");
+        println!(
+            "\n[⏳ Activating feature in {} seconds; CTRL+C to abort]\n",
+            args.activation_delay
+        );
     }
 
     // 3) Start client transactions.
 
-    let progress = MultiProgress::new();
+    let recorder = Recorder::default();
+    let invariants = Invariants::default();
+
+    let workload = match args.tps {
+        Some(tps) => Workload::spawn_open_loop(
+            &context,
+            1..args.clients + 1,
+            &args.mix,
+            OpenLoop {
+                tps,
+                max_in_flight: args.max_in_flight,
+            },
+            recorder.clone(),
+            &interrupted,
+        ),
+        None => Workload::spawn(
+            &context,
+            1..args.clients + 1,
+            &args.mix,
+            recorder.clone(),
+            invariants.clone(),
+            &interrupted,
+        ),
+    };
+
+    let clients = ClientRegistry::default();
+    clients.register(workload.stats());
+
+    let state = Arc::new(RunState::new(clients));
 
-    for i in 0..CLIENT_THREADS {
+    // CU monitoring thread.
+    {
         let rpc_client = context.test_validator.get_async_rpc_client();
         let payer = Keypair::try_from(context.payer.to_bytes().as_slice()).unwrap();
-
-        let pb = progress.add(ProgressBar::no_length());
+        let units = state.units.clone();
+        let mix = args.mix.clone();
         let interrupted = interrupted.clone();
 
-        spawn(async move { start_client(i + 1, pb, rpc_client, payer, interrupted).await });
+        spawn(async move { start_monitor(units, rpc_client, payer, mix, interrupted).await });
     }
 
-    let upgraded = Arc::new(AtomicBool::new(upgraded));
-
-    // CU monitoring thread.
+    // Cluster polling thread.
     {
         let rpc_client = context.test_validator.get_async_rpc_client();
-        let payer = Keypair::try_from(context.payer.to_bytes().as_slice()).unwrap();
-        let upgraded = upgraded.clone();
+        let bank_forks = context.test_validator.bank_forks();
+        let features = targets
+            .iter()
+            .map(|target| (target.name.clone(), target.feature_id))
+            .collect();
+        let state = state.clone();
+        let interrupted = interrupted.clone();
+
+        spawn(async move {
+            poll_cluster(
+                state,
+                rpc_client,
+                bank_forks,
+                SPL_TOKEN_PROGRAM_ID,
+                features,
+                interrupted,
+            )
+            .await
+        });
+    }
 
-        let pb = progress.add(ProgressBar::no_length());
+    // Metrics endpoint.
+    if let Some(address) = args.metrics_addr {
+        let listener = metrics::bind(address).await?;
+        let state = state.clone();
         let interrupted = interrupted.clone();
 
-        spawn(async move { start_monitor(pb, upgraded, rpc_client, payer, interrupted).await });
+        println!("📈 Serving metrics on http://{address}/metrics\n");
+
+        spawn(async move { metrics::serve(listener, state, interrupted).await });
     }
 
+    // Live display: either the dashboard or periodic progress lines.
+    let activate = Arc::new(Notify::new());
+
+    let display = {
+        let state = state.clone();
+        let interrupted = interrupted.clone();
+
+        if args.headless {
+            spawn(async move { print_progress(state, interrupted).await })
+        } else {
+            let activate = activate.clone();
+            spawn_blocking(move || dashboard::run(state, activate, interrupted))
+        }
+    };
+
     // 4) If the program has not been upgraded, wait for feature
-    // activation (10 seconds).
-    if !upgraded.load(Ordering::SeqCst) {
-        sleep(Duration::from_secs(10));
+    // activation, either after the delay or when requested from the
+    // dashboard.
+    let mut timeline = if upgraded {
+        MigrationTimeline::upgraded()
+    } else {
+        MigrationTimeline::default()
+    };
+
+    if !upgraded {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(args.activation_delay)) => (),
+            _ = activate.notified() => (),
+            _ = interrupted.cancelled() => (),
+        }
+    }
 
-        context.activate_feature(&ID).await;
+    let mut migrated = true;
+
+    if !upgraded && !interrupted.is_cancelled() {
+        if let Err(error) = activate_targets(
+            &context,
+            &pending,
+            args.activation_order.into(),
+            &mut timeline,
+            &interrupted,
+        )
+        .await
+        {
+            println!("\n🟥 Activation failed: {error}");
+            migrated = false;
+        }
 
-        context.wait_for_next_epoch().await;
+        invariants.request_check();
+    }
 
-        // Check that the program has been upgraded.
-        context
-            .assert_owner(
-                &SPL_TOKEN_PROGRAM_ID,
-                &solana_sdk_ids::bpf_loader_upgradeable::id(),
+    // 5) Run for the requested number of slots after activation or sleep
+    // until CTRL+C is pressed.
+    let completed = if let Some(run_slots) = args.run_slots {
+        let start_slot = match timeline.activation_slot {
+            Some(slot) => slot,
+            None => context.get_slot().await,
+        };
+
+        let completed = interrupted
+            .run_until_cancelled(
+                context.wait_until_slot(start_slot + run_slots, CommitmentConfig::processed()),
             )
-            .await;
+            .await
+            .is_some();
 
-        upgraded.store(true, Ordering::SeqCst);
+        interrupted.cancel();
+        completed
+    } else {
+        interrupted.cancelled().await;
+        false
+    };
+
+    workload.stop().await;
+    let _ = display.await;
+
+    let summary = report_summary(&recorder, &timeline, &args.report)?;
+    let invariants_held = report_invariants(&invariants);
+    let programs_verified = verify_programs(&context, &migration_targets, &timeline).await;
+
+    let exit_code = if args.run_slots.is_some() {
+        let violations = Thresholds::from(&args.thresholds).check(&summary);
+
+        if !completed {
+            println!("\n🟥 Run interrupted before completion.");
+            ExitCode::FAILURE
+        } else if violations.is_empty() {
+            println!("\n🟩 All thresholds met.");
+            ExitCode::SUCCESS
+        } else {
+            for violation in violations {
+                println!("\n🟥 Threshold broken: {violation}");
+            }
+            ExitCode::FAILURE
+        }
+    } else {
+        ExitCode::SUCCESS
+    };
+
+    let exit_code = if migrated && invariants_held && programs_verified {
+        exit_code
+    } else {
+        ExitCode::FAILURE
+    };
+
+    println!("\n🟨 Shutting down validator...");
+
+    Ok(exit_code)
+}
+
+async fn run_scenario(args: ScenarioArgs) -> Result<ExitCode> {
+    let scenario = Scenario::load(&args.path)?;
+    let interrupted = interrupt_handler();
+
+    println!("p-token migration simulator");
+    println!("---------------------------");
+
+    println!("\n📜 Scenario: {}", args.path.display());
+
+    println!("\n⚙️  Starting test validator\n",);
+
+    let (context, origin) = args.validator.start(&scenario.migration_targets()).await?;
+    print_ledger_origin(&origin, &args.validator);
+
+    println!("...done ✅\n");
+
+    let clients = ClientRegistry::default();
+    let recorder = Recorder::default();
+    let invariants = Invariants::default();
+    let mut timeline = MigrationTimeline::default();
+
+    // Metrics endpoint, together with the cluster polling it reports.
+    let stopped = interrupted.child_token();
+
+    if let Some(address) = args.metrics_addr {
+        let listener = metrics::bind(address).await?;
+        let state = Arc::new(RunState::new(clients.clone()));
+
+        {
+            let rpc_client = context.test_validator.get_async_rpc_client();
+            let bank_forks = context.test_validator.bank_forks();
+            let features = scenario
+                .targets
+                .iter()
+                .map(|target| (target.name.clone(), target.feature_id))
+                .collect();
+            let state = state.clone();
+            let stopped = stopped.clone();
+
+            spawn(async move {
+                poll_cluster(
+                    state,
+                    rpc_client,
+                    bank_forks,
+                    SPL_TOKEN_PROGRAM_ID,
+                    features,
+                    stopped,
+                )
+                .await
+            });
+        }
+
+        println!("📈 Serving metrics on http://{address}/metrics\n");
+
+        let stopped = stopped.clone();
+        spawn(async move { metrics::serve(listener, state, stopped).await });
     }
 
-    // Sleep until CTRL+C is pressed.
-    while !interrupted.load(Ordering::SeqCst) {
-        sleep(Duration::from_secs(5));
+    let result = scenario
+        .run(
+            &context,
+            &clients,
+            &recorder,
+            &invariants,
+            &mut timeline,
+            &interrupted,
+        )
+        .await;
+
+    stopped.cancel();
+
+    report_summary(&recorder, &timeline, &args.report)?;
+
+    match result {
+        Ok(()) if !report_invariants(&invariants) => {
+            println!("\n🟥 Scenario failed: token state invariants violated");
+            Ok(ExitCode::FAILURE)
+        }
+        Ok(()) if interrupted.is_cancelled() => {
+            println!("\n🟨 Scenario interrupted, shutting down validator...");
+            Ok(ExitCode::FAILURE)
+        }
+        Ok(()) => {
+            println!("\n🟩 Scenario completed, shutting down validator...");
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(error) => {
+            println!("\n🟥 Scenario failed: {error}");
+            Ok(ExitCode::FAILURE)
+        }
     }
+}
 
-    println!("\n🟨 Shutting down validator...");
+async fn run_diff(args: DiffArgs) -> Result<ExitCode> {
+    let interrupted = interrupt_handler();
+
+    println!("p-token migration simulator");
+    println!("---------------------------");
+
+    println!("\n⚙️  Starting SPL Token and p-token validators\n",);
+
+    let context =
+        DiffContext::start(&args.elf_directory, &args.ledger_path, args.slots_per_epoch).await?;
+
+    let fixture = Fixture::default();
+    context.create_fixture(&fixture).await;
+
+    println!("...done ✅");
+
+    let kinds = if args.mix.is_empty() {
+        InstructionKind::ALL.to_vec()
+    } else {
+        args.mix.clone()
+    };
+
+    println!("\n🔬 Comparing {} instructions\n", kinds.len());
+
+    let rpc_client = context.spl_token.test_validator.get_async_rpc_client();
+    let mut state = FixtureState::default();
+    let mut divergences = Vec::new();
+
+    for kind in kinds {
+        if interrupted.is_cancelled() {
+            break;
+        }
+
+        let mut found = 0;
+
+        for _ in 0..args.iterations {
+            let operation = Operation::new(kind, &fixture, &state, &context.payer.pubkey());
+            let differences = context
+                .execute(&operation, &fixture, args.ignore_logs)
+                .await;
+
+            found += differences.len();
+            divergences.extend(differences);
+
+            state.refresh(&rpc_client, &fixture).await;
+        }
+
+        if found == 0 {
+            println!("  ✅ {kind}");
+        } else {
+            println!("  ❌ {kind}: {found} differences");
+        }
+    }
+
+    if divergences.is_empty() {
+        println!("\n🟩 No differences found, shutting down validators...");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("\n🐞 Compatibility issues:\n");
+
+    for divergence in &divergences {
+        println!("{divergence}\n");
+    }
+
+    println!(
+        "🟥 {} differences found, shutting down validators...",
+        divergences.len()
+    );
+
+    Ok(ExitCode::FAILURE)
+}
+
+async fn run_bench(args: BenchArgs) -> Result<ExitCode> {
+    println!("p-token migration simulator");
+    println!("---------------------------");
+
+    println!("\n⚙️  Starting test validator\n",);
+
+    let (context, origin) = args.validator.start(&[P_TOKEN_MIGRATION]).await?;
+    print_ledger_origin(&origin, &args.validator);
+
+    let rpc_client = context.test_validator.get_async_rpc_client();
+
+    if context
+        .get_account(&SPL_TOKEN_PROGRAM_ID)
+        .await
+        .is_some_and(|account| account.owner == bpf_loader_upgradeable::id())
+    {
+        println!("\n🟥 SPL Token already upgraded; run 'make clean-ledger' to use a new ledger.");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let fixtures = BenchFixtures::create(&rpc_client, &context.payer).await;
+
+    println!("...done ✅");
+
+    let kinds = if args.mix.is_empty() {
+        InstructionKind::ALL.to_vec()
+    } else {
+        args.mix.clone()
+    };
+    let variants = Variant::of(&kinds);
+
+    println!(
+        "\n📏 Measuring {} instruction variants on SPL Token",
+        variants.len()
+    );
+
+    let spl_token = fixtures
+        .measure(&rpc_client, &context.payer, &variants)
+        .await;
+
+    println!("\n🚀 Activating feature and waiting for the migration");
+
+    if let Err(error) = activate_targets(
+        &context,
+        &[P_TOKEN_MIGRATION],
+        ActivationOrder::Sequential,
+        &mut MigrationTimeline::default(),
+        &CancellationToken::new(),
+    )
+    .await
+    {
+        println!("\n🟥 Activation failed: {error}");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    context
+        .wait_for_program(&SPL_TOKEN_PROGRAM_ID, &bpf_loader_upgradeable::id())
+        .await;
+
+    println!(
+        "\n📏 Measuring {} instruction variants on p-token\n",
+        variants.len()
+    );
+
+    let p_token = fixtures
+        .measure(&rpc_client, &context.payer, &variants)
+        .await;
+
+    let report = BenchReport::new(spl_token, p_token);
+
+    println!("{report}");
+
+    if let Some(path) = &args.output {
+        report.write_csv(BufWriter::new(File::create(path)?))?;
+        println!("📝 Results written to {}", path.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn run_snapshot(args: SnapshotArgs) -> Result<ExitCode> {
+    println!("p-token migration simulator");
+    println!("---------------------------");
+
+    println!("\n⚙️  Starting test validator\n",);
+
+    // The snapshot must be taken from a new ledger, before the activation.
+    if args.ledger_path.exists() {
+        std::fs::remove_dir_all(&args.ledger_path)?;
+    }
+
+    let context = ValidatorContext::start(
+        &[P_TOKEN_MIGRATION],
+        &args.elf_directory,
+        &args.ledger_path,
+        args.slots_per_epoch,
+        &args.account_dumps,
+    )
+    .await?;
+
+    println!("...done ✅");
+
+    println!("\n👥 Creating {} client fixtures", args.clients);
+
+    let rpc_client = context.test_validator.get_async_rpc_client();
+    let mut fixtures = Vec::new();
+
+    for _ in 0..args.clients {
+        let fixture = Fixture::default();
+        fixture.create(&rpc_client, &context.payer).await;
+        fixtures.push(fixture);
+    }
+
+    let slot = context.get_slot().await;
+
+    println!("\n📸 Waiting for a snapshot after slot {slot}");
+
+    let manifest = snapshot::create(
+        &args.ledger_path,
+        &args.output,
+        slot,
+        args.slots_per_epoch,
+        &fixtures,
+    )
+    .await?;
+
+    println!(
+        "\n🟩 Snapshot of slot {} written to {}, shutting down validator...",
+        manifest.slot,
+        args.output.display()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn run_feature_status(args: FeatureStatusArgs) -> Result<ExitCode> {
+    println!("p-token migration simulator");
+    println!("---------------------------");
+
+    let targets = match &args.targets {
+        Some(path) => Scenario::load(path)?.targets,
+        None => scenario::default_targets(),
+    };
+
+    println!("\n📖 Reading ledger: {}\n", args.ledger_path.display());
+
+    let (slot, features) = read_ledger_features(
+        &args.ledger_path,
+        &targets
+            .iter()
+            .map(|target| target.feature_id)
+            .collect::<Vec<_>>(),
+    )
+    .await?;
+
+    println!("...done ✅");
+
+    println!("\n🔍 Feature status at slot {slot}\n");
+
+    for (target, feature) in targets.iter().zip(features) {
+        println!("Feature: {} ({})", feature.feature_id, target.name);
+        println!("  State: {}", feature.state());
+        println!("  Owner: {}", feature.owner_label());
+        println!(
+            "  Activated at: {}",
+            feature
+                .activated_at
+                .map_or("-".to_string(), |slot| format!("slot {slot}"))
+        );
+        println!(
+            "  Runtime: {}\n",
+            if feature.runtime_active {
+                "active"
+            } else {
+                "inactive"
+            }
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// Prints where the ledger of the validator comes from, unless it is new.
+fn print_ledger_origin(origin: &LedgerOrigin, args: &ValidatorArgs) {
+    match (origin, &args.from_snapshot) {
+        (LedgerOrigin::Existing, _) => println!(
+            "  + 🗂️ Existing ledger found: {}",
+            args.ledger_path.display()
+        ),
+        (LedgerOrigin::Snapshot(manifest), Some(snapshot)) => println!(
+            "  + 📸 Snapshot of slot {} restored: {}",
+            manifest.slot,
+            snapshot.display()
+        ),
+        _ => (),
+    }
+}
+
+// Verifies the program accounts of the upgraded targets and prints the
+// results, returning whether all checks passed.
+async fn verify_programs(
+    context: &ValidatorContext,
+    targets: &[MigrationTarget<'_>],
+    timeline: &MigrationTimeline,
+) -> bool {
+    let verifications = verify_targets(context, targets, timeline).await;
+    let mut verified = true;
+
+    println!("\n🔎 Program verification\n");
+
+    for (target, verification) in targets.iter().zip(verifications) {
+        match verification {
+            Some(verification) => {
+                verified &= verification.passed();
+                println!("{verification}");
+            }
+            None => println!("Program: {} (not upgraded)", target.program_id),
+        }
+    }
+
+    verified
+}
+
+// Prints the result of the invariant checks, returning whether all of them
+// held.
+fn report_invariants(invariants: &Invariants) -> bool {
+    let violations = invariants.violations();
+
+    println!(
+        "\n🧮 Invariants: {} checks, {} violations",
+        invariants.checks(),
+        violations.len()
+    );
+
+    for violation in &violations {
+        println!("  ❌ {violation}");
+    }
+
+    violations.is_empty()
+}
+
+// Summarizes the run, writing the transaction report if requested, and
+// prints the summary.
+fn report_summary(
+    recorder: &Recorder,
+    timeline: &MigrationTimeline,
+    report_args: &ReportArgs,
+) -> Result<RunSummary> {
+    let summary = finish_run(
+        recorder,
+        timeline,
+        report_args
+            .path
+            .as_deref()
+            .map(|path| (path, report_args.format.into())),
+    )?;
+
+    if let Some(path) = &report_args.path {
+        println!("\n📝 Transaction report: {}", path.display());
+    }
+
+    println!("\n📊 Summary\n");
+    println!("{summary}");
 
-    Ok(())
+    Ok(summary)
 }
@@ -0,0 +1,247 @@
+//! Prometheus metrics of a run, served on a local `/metrics` endpoint.
+
+use {
+    crate::dashboard::RunState,
+    p_token_migration_harness::{
+        client::ClientStats, error::ErrorCategory, validator::FeatureState,
+    },
+    std::{
+        fmt::Write as _,
+        io::Result,
+        net::SocketAddr,
+        sync::{atomic::Ordering, Arc},
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        spawn,
+    },
+    tokio_util::sync::CancellationToken,
+};
+
+/// Prefix of every metric name.
+const PREFIX: &str = "p_token_simulator";
+
+/// Maximum size of the head of a request.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Binds the metrics endpoint to `address`.
+pub async fn bind(address: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(address).await
+}
+
+/// Serves the metrics of the run until `interrupted` is cancelled.
+///
+/// Only `GET /metrics` is supported; every connection is closed after the
+/// response.
+pub async fn serve(listener: TcpListener, state: Arc<RunState>, interrupted: CancellationToken) {
+    while let Some(accepted) = interrupted.run_until_cancelled(listener.accept()).await {
+        let Ok((stream, _)) = accepted else {
+            continue;
+        };
+        let state = state.clone();
+
+        spawn(async move {
+            let _ = respond(stream, &state).await;
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, state: &RunState) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    // Read the whole request head, which ends with an empty line.
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() >= MAX_REQUEST_SIZE {
+            return Ok(());
+        }
+
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let (status, body) = if request.starts_with(b"GET /metrics ") {
+        ("200 OK", render(state))
+    } else {
+        ("404 Not Found", String::new())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// Renders the metrics in the Prometheus text format.
+fn render(state: &RunState) -> String {
+    let mut out = String::new();
+
+    client_metric(
+        &mut out,
+        state,
+        ("transactions_sent_total", "counter"),
+        "Transactions sent by each client.",
+        ClientStats::sent,
+    );
+    client_metric(
+        &mut out,
+        state,
+        ("transactions_confirmed_total", "counter"),
+        "Transactions confirmed without errors, by client.",
+        ClientStats::success,
+    );
+
+    header(
+        &mut out,
+        "transactions_failed_total",
+        "counter",
+        "Failed transactions, by client and error class.",
+    );
+    for client in &state.clients.clients() {
+        for category in ErrorCategory::ALL {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_transactions_failed_total{{client=\"{}\",error_class=\"{}\"}} {}",
+                client.id,
+                category.label(),
+                client.errors(category)
+            );
+        }
+    }
+
+    client_metric(
+        &mut out,
+        state,
+        ("transactions_in_flight", "gauge"),
+        "Transactions waiting for confirmation, by client.",
+        ClientStats::in_flight,
+    );
+    client_metric(
+        &mut out,
+        state,
+        ("sends_dropped_total", "counter"),
+        "Open-loop sends skipped because too many transactions were in flight, by client.",
+        ClientStats::dropped,
+    );
+
+    header(
+        &mut out,
+        "confirmation_latency_seconds",
+        "histogram",
+        "Time from sending a transaction to its confirmation, by client.",
+    );
+    for client in &state.clients.clients() {
+        let latency = client.latency();
+        let mut cumulative = 0;
+
+        for (bound, count) in latency.buckets() {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{PREFIX}_confirmation_latency_seconds_bucket{{client=\"{}\",le=\"{bound}\"}} \
+                 {cumulative}",
+                client.id
+            );
+        }
+
+        let count = latency.count();
+        let sum = latency.sum().as_secs_f64();
+
+        let _ = writeln!(
+            out,
+            "{PREFIX}_confirmation_latency_seconds_bucket{{client=\"{}\",le=\"+Inf\"}} {count}",
+            client.id
+        );
+        let _ = writeln!(
+            out,
+            "{PREFIX}_confirmation_latency_seconds_sum{{client=\"{}\"}} {sum}",
+            client.id
+        );
+        let _ = writeln!(
+            out,
+            "{PREFIX}_confirmation_latency_seconds_count{{client=\"{}\"}} {count}",
+            client.id
+        );
+    }
+
+    header(
+        &mut out,
+        "instruction_compute_units",
+        "gauge",
+        "Compute units of the last simulation of each instruction.",
+    );
+    for (kind, units) in state.units.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "{PREFIX}_instruction_compute_units{{instruction=\"{}\"}} {units}",
+            kind.name()
+        );
+    }
+
+    header(&mut out, "slot", "gauge", "Current slot of the validator.");
+    let _ = writeln!(out, "{PREFIX}_slot {}", state.slot.load(Ordering::Relaxed));
+
+    header(
+        &mut out,
+        "epoch",
+        "gauge",
+        "Current epoch of the validator.",
+    );
+    let _ = writeln!(
+        out,
+        "{PREFIX}_epoch {}",
+        state.epoch.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "feature_state",
+        "gauge",
+        "Lifecycle state of the feature of each target; 1 for the current state.",
+    );
+    for (target, feature) in state.features.lock().unwrap().iter() {
+        let current = feature.state().name();
+
+        for name in FeatureState::NAMES {
+            let _ = writeln!(
+                out,
+                "{PREFIX}_feature_state{{target=\"{target}\",state=\"{name}\"}} {}",
+                u8::from(name == current)
+            );
+        }
+    }
+
+    out
+}
+
+// Writes a metric with a value for each client.
+fn client_metric(
+    out: &mut String,
+    state: &RunState,
+    (name, kind): (&str, &str),
+    help: &str,
+    value: fn(&ClientStats) -> u64,
+) {
+    header(out, name, kind, help);
+
+    for client in &state.clients.clients() {
+        let _ = writeln!(
+            out,
+            "{PREFIX}_{name}{{client=\"{}\"}} {}",
+            client.id,
+            value(client)
+        );
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {PREFIX}_{name} {help}");
+    let _ = writeln!(out, "# TYPE {PREFIX}_{name} {kind}");
+}
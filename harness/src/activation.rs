@@ -0,0 +1,91 @@
+//! Activation of the features of the migration targets.
+
+use {
+    crate::{
+        report::MigrationTimeline,
+        validator::{MigrationTarget, ValidatorContext},
+    },
+    solana_sdk_ids::bpf_loader_upgradeable,
+    std::io,
+    tokio_util::sync::CancellationToken,
+};
+
+/// Order in which the features of the targets are activated.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ActivationOrder {
+    /// One target per epoch, in the order of the targets.
+    #[default]
+    Sequential,
+    /// All targets in the same epoch.
+    Together,
+}
+
+/// Activates the features of the targets and checks that each program is
+/// upgraded at the start of the epoch following its activation.
+///
+/// The activation and migration slots of each target are recorded in the
+/// `timeline`. When `interrupted` is cancelled, the remaining targets are
+/// not activated.
+///
+/// Fails when an activation transaction fails or the program of a target is
+/// not owned by the upgradeable loader once its feature is active; the
+/// remaining targets are not activated.
+pub async fn activate_targets(
+    context: &ValidatorContext,
+    targets: &[MigrationTarget<'_>],
+    order: ActivationOrder,
+    timeline: &mut MigrationTimeline,
+    interrupted: &CancellationToken,
+) -> io::Result<()> {
+    let groups = match order {
+        ActivationOrder::Sequential => targets.chunks(1).collect::<Vec<_>>(),
+        ActivationOrder::Together => vec![targets],
+    };
+
+    for group in groups {
+        if interrupted.is_cancelled() {
+            break;
+        }
+
+        // Features of the same group are activated in a single transaction.
+        let feature_ids = group
+            .iter()
+            .map(|target| target.feature_id)
+            .collect::<Vec<_>>();
+        let slot = context.activate_features(&feature_ids).await?;
+
+        for target in group {
+            timeline.record_activation(target.name, slot);
+        }
+
+        // The programs are migrated when the runtime activates the features,
+        // at the next epoch boundary.
+        let Some(migration_slot) = interrupted
+            .run_until_cancelled(context.wait_until_feature_active(&feature_ids[0]))
+            .await
+        else {
+            break;
+        };
+
+        // Check that the programs have been upgraded.
+        for target in group {
+            let owner = context
+                .get_account(&target.program_id)
+                .await
+                .map(|account| account.owner);
+
+            if owner != Some(bpf_loader_upgradeable::id()) {
+                return Err(io::Error::other(format!(
+                    "program of '{}' not migrated: {} owned by {}",
+                    target.name,
+                    target.program_id,
+                    owner.map_or("-".to_string(), |owner| owner.to_string())
+                )));
+            }
+
+            timeline.record_migration(target.name, migration_slot);
+        }
+    }
+
+    Ok(())
+}
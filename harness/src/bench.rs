@@ -0,0 +1,213 @@
+//! Compute unit benchmark of token instructions.
+//!
+//! Every instruction variant is simulated before and after the migration to
+//! compare the compute units consumed by SPL Token and p-token.
+
+use {
+    crate::{
+        client::simulate_units,
+        workload::{Fixture, FixtureState, InstructionKind, Operation},
+    },
+    serde::Serialize,
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{signature::Keypair, signer::Signer},
+    std::{
+        collections::BTreeMap,
+        fmt,
+        io::{Result, Write},
+    },
+};
+
+/// Instruction variant measured by the benchmark.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Variant {
+    /// Token instruction of the variant.
+    pub kind: InstructionKind,
+    /// Whether the authority of the instruction is a multisig account.
+    pub multisig: bool,
+}
+
+impl Variant {
+    /// Returns the variants of the given instructions, including multisig
+    /// variants of instructions signed by an authority.
+    pub fn of(kinds: &[InstructionKind]) -> Vec<Variant> {
+        kinds
+            .iter()
+            .flat_map(|kind| {
+                [false, true]
+                    .into_iter()
+                    .filter(|multisig| !multisig || kind.has_authority())
+                    .map(|multisig| Variant {
+                        kind: *kind,
+                        multisig,
+                    })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.multisig {
+            write!(f, "{} (multisig)", self.kind)
+        } else {
+            write!(f, "{}", self.kind)
+        }
+    }
+}
+
+/// Compute units consumed by each variant or the error of its simulation.
+pub type Measurements = BTreeMap<Variant, std::result::Result<u64, String>>;
+
+/// Fixtures used to measure the instruction variants.
+pub struct BenchFixtures {
+    single: Fixture,
+    multisig: Fixture,
+}
+
+impl BenchFixtures {
+    /// Creates the accounts of the fixtures, paid by `payer`.
+    pub async fn create(rpc_client: &RpcClient, payer: &Keypair) -> Self {
+        let fixtures = Self {
+            single: Fixture::default(),
+            multisig: Fixture::multisig(),
+        };
+
+        fixtures.single.create(rpc_client, payer).await;
+        fixtures.multisig.create(rpc_client, payer).await;
+
+        fixtures
+    }
+
+    /// Simulates each variant, returning the compute units consumed or the
+    /// error of the simulation.
+    pub async fn measure(
+        &self,
+        rpc_client: &RpcClient,
+        payer: &Keypair,
+        variants: &[Variant],
+    ) -> Measurements {
+        // Simulations do not change the accounts, so the state never changes.
+        let state = FixtureState::default();
+        let mut units = BTreeMap::new();
+
+        for variant in variants {
+            let fixture = if variant.multisig {
+                &self.multisig
+            } else {
+                &self.single
+            };
+            let operation = Operation::new(variant.kind, fixture, &state, &payer.pubkey());
+
+            units.insert(
+                *variant,
+                simulate_units(rpc_client, payer, &operation, fixture).await,
+            );
+        }
+
+        units
+    }
+}
+
+/// Compute units of a variant on SPL Token and p-token.
+#[derive(Serialize)]
+struct BenchRow {
+    instruction: String,
+    spl_token: Option<u64>,
+    p_token: Option<u64>,
+    /// Percentage of compute units saved by p-token.
+    saved: Option<f64>,
+    /// Errors of failed simulations.
+    error: Option<String>,
+}
+
+/// Comparison of the compute units consumed before and after the migration.
+pub struct BenchReport {
+    rows: Vec<BenchRow>,
+}
+
+impl BenchReport {
+    /// Compares the measurements of each variant on SPL Token with those on
+    /// p-token.
+    pub fn new(spl_token: Measurements, mut p_token: Measurements) -> Self {
+        let rows = spl_token
+            .into_iter()
+            .map(|(variant, before)| {
+                let after = p_token
+                    .remove(&variant)
+                    .unwrap_or_else(|| Err("not measured".to_string()));
+
+                let saved = match (&before, &after) {
+                    (Ok(before), Ok(after)) if *before > 0 => {
+                        Some(100.0 * (*before as f64 - *after as f64) / *before as f64)
+                    }
+                    _ => None,
+                };
+                let error = match (&before, &after) {
+                    (Err(error), _) => Some(format!("spl-token: {error}")),
+                    (_, Err(error)) => Some(format!("p-token: {error}")),
+                    _ => None,
+                };
+
+                BenchRow {
+                    instruction: variant.to_string(),
+                    spl_token: before.ok(),
+                    p_token: after.ok(),
+                    saved,
+                    error,
+                }
+            })
+            .collect();
+
+        Self { rows }
+    }
+
+    /// Writes the report as CSV, with a header row.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for row in &self.rows {
+            writer.serialize(row)?;
+        }
+        writer.flush()
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = |units: Option<u64>| units.map_or("-".to_string(), |u| u.to_string());
+
+        writeln!(
+            f,
+            "| {:<32} | {:>9} | {:>9} | {:>7} |",
+            "Instruction", "SPL Token", "p-token", "Saved"
+        )?;
+        writeln!(f, "| {:-<32} | {:->9} | {:->9} | {:->7} |", "", "", "", "")?;
+
+        for row in &self.rows {
+            writeln!(
+                f,
+                "| {:<32} | {:>9} | {:>9} | {:>7} |",
+                row.instruction,
+                units(row.spl_token),
+                units(row.p_token),
+                row.saved
+                    .map_or("-".to_string(), |saved| format!("{saved:.1}%")),
+            )?;
+        }
+
+        let errors = self
+            .rows
+            .iter()
+            .filter_map(|row| Some((&row.instruction, row.error.as_ref()?)))
+            .collect::<Vec<_>>();
+
+        if !errors.is_empty() {
+            writeln!(f, "\nFailed simulations:")?;
+            for (instruction, error) in errors {
+                writeln!(f, "  {instruction}: {error}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
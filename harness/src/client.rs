@@ -0,0 +1,1170 @@
+//! Clients sending token transactions to the test validator.
+//!
+//! A [`Workload`] runs a group of clients, either closed-loop, each waiting
+//! for the confirmation of a transaction before sending the next one, or
+//! open-loop, sending transactions at a target rate.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    config::{CommitmentConfig, RpcSendTransactionConfig},
+    request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+    response::{RpcResult, RpcSimulateTransactionResult},
+};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use tokio::{
+    spawn,
+    sync::{
+        mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender},
+        watch, OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinHandle,
+    time::{interval, sleep, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    error::{ClientError, ErrorCategory},
+    invariant::{FixtureTracker, Invariants},
+    report::{Recorder, TransactionRecord},
+    validator::ValidatorContext,
+    workload::{Fixture, FixtureState, InstructionKind, InstructionMix, Operation},
+};
+
+/// Interval between refreshes of the blockhash used by open-loop clients.
+const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Interval between signature status requests of the clients, which is
+/// also the resolution of the processed and confirmed latencies.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval between signature status requests for the finalization of
+/// confirmed transactions.
+const FINALIZATION_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Time after which a transaction without a confirmed status is reported as
+/// a confirmation timeout; longer than the lifetime of its blockhash.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Compute unit limit of open-loop transactions, decreased by their sequence
+/// number so that transactions with the same instructions and blockhash
+/// have different signatures.
+const COMPUTE_UNIT_LIMIT: u32 = 400_000;
+
+/// Number of distinct compute unit limits of open-loop transactions.
+const UNIQUE_LIMITS: u32 = 200_000;
+
+/// Upper bounds (in seconds) of the confirmation latency buckets.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0];
+
+/// Histogram of confirmation latencies, updated by the clients.
+#[derive(Default)]
+pub struct Histogram {
+    /// Number of observations of each bucket, not cumulative.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    /// Sum of the observations, in microseconds.
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    /// Records a confirmation latency.
+    pub fn observe(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Upper bound (in seconds) and number of observations of each bucket,
+    /// not cumulative.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, count)| (*bound, count.load(Ordering::Relaxed)))
+    }
+
+    /// Number of observations.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Sum of the observations.
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum.load(Ordering::Relaxed))
+    }
+}
+
+/// Live counters of a client.
+pub struct ClientStats {
+    /// Id of the client.
+    pub id: u64,
+    success: AtomicU64,
+    errors: [AtomicU64; ErrorCategory::ALL.len()],
+    sent: AtomicU64,
+    in_flight: AtomicU64,
+    dropped: AtomicU64,
+    latency: Histogram,
+}
+
+impl ClientStats {
+    /// Counters of the client with the given id, all zero.
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            success: AtomicU64::default(),
+            errors: Default::default(),
+            sent: AtomicU64::default(),
+            in_flight: AtomicU64::default(),
+            dropped: AtomicU64::default(),
+            latency: Histogram::default(),
+        }
+    }
+
+    /// Number of successful transactions.
+    pub fn success(&self) -> u64 {
+        self.success.load(Ordering::Relaxed)
+    }
+
+    /// Number of failed transactions of the given category.
+    pub fn errors(&self, category: ErrorCategory) -> u64 {
+        self.errors[category as usize].load(Ordering::Relaxed)
+    }
+
+    /// Number of failed transactions, of any category.
+    pub fn total_errors(&self) -> u64 {
+        self.errors
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Number of transactions sent.
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Number of transactions sent and waiting for confirmation.
+    pub fn in_flight(&self) -> u64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Number of open-loop sends skipped because too many transactions were
+    /// in flight.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Confirmation latency of the successful transactions.
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    // Records the outcome of a transaction, with the time from sending it to
+    // its confirmation.
+    fn record(&self, error: Option<&ClientError>, latency: Option<Duration>) {
+        let counter = match error {
+            Some(error) => &self.errors[error.class.category() as usize],
+            None => &self.success,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        if let (None, Some(latency)) = (error, latency) {
+            self.latency.observe(latency);
+        }
+    }
+}
+
+/// Collects the statistics of the clients of a run, as their workloads
+/// start.
+///
+/// The registry can be cloned and shared, e.g. with a metrics endpoint.
+#[derive(Clone, Default)]
+pub struct ClientRegistry {
+    clients: Arc<Mutex<Vec<Arc<ClientStats>>>>,
+}
+
+impl ClientRegistry {
+    /// Adds the statistics of the clients of a workload.
+    pub fn register(&self, clients: &[Arc<ClientStats>]) {
+        self.clients.lock().unwrap().extend(clients.iter().cloned());
+    }
+
+    /// Returns the statistics of the clients registered so far.
+    pub fn clients(&self) -> Vec<Arc<ClientStats>> {
+        self.clients.lock().unwrap().clone()
+    }
+}
+
+/// Rate of an open-loop workload.
+#[derive(Clone, Copy)]
+pub struct OpenLoop {
+    /// Number of transactions sent per second by all the clients.
+    pub tps: u64,
+    /// Maximum number of transactions waiting for confirmation.
+    pub max_in_flight: usize,
+}
+
+/// Group of clients sending transactions until stopped.
+pub struct Workload {
+    stop: CancellationToken,
+    handles: Vec<JoinHandle<()>>,
+    stats: Vec<Arc<ClientStats>>,
+}
+
+impl Workload {
+    /// Spawns one client for each of the provided ids.
+    ///
+    /// Clients send instructions from the `mix` until the workload is stopped
+    /// or `interrupted` is cancelled, recording the outcome of their
+    /// transactions in the `recorder` and checking the state of their
+    /// accounts against the `invariants`.
+    pub fn spawn(
+        context: &ValidatorContext,
+        ids: Range<u64>,
+        mix: &InstructionMix,
+        recorder: Recorder,
+        invariants: Invariants,
+        interrupted: &CancellationToken,
+    ) -> Self {
+        let stop = interrupted.child_token();
+        let stats = ids
+            .map(|id| Arc::new(ClientStats::new(id)))
+            .collect::<Vec<_>>();
+
+        let handles = stats
+            .iter()
+            .map(|stats| {
+                let rpc_client = Arc::new(context.test_validator.get_async_rpc_client());
+                let payer = Keypair::try_from(context.payer.to_bytes().as_slice()).unwrap();
+                let fixture = context.take_fixture();
+
+                let stats = stats.clone();
+                let mix = mix.clone();
+                let recorder = recorder.clone();
+                let invariants = invariants.clone();
+                let stop = stop.clone();
+
+                spawn(async move {
+                    start_client(
+                        stats, rpc_client, payer, fixture, mix, recorder, invariants, stop,
+                    )
+                    .await
+                })
+            })
+            .collect();
+
+        Self {
+            stop,
+            handles,
+            stats,
+        }
+    }
+
+    /// Spawns one open-loop client for each of the provided ids.
+    ///
+    /// Together, the clients send transactions at the target `rate` without
+    /// waiting for their confirmation, which is collected by polling their
+    /// signature status. The fixture state is not checked against any
+    /// invariants, since many transactions are in flight at any time.
+    pub fn spawn_open_loop(
+        context: &ValidatorContext,
+        ids: Range<u64>,
+        mix: &InstructionMix,
+        rate: OpenLoop,
+        recorder: Recorder,
+        interrupted: &CancellationToken,
+    ) -> Self {
+        let stop = interrupted.child_token();
+        let stats = ids
+            .map(|id| Arc::new(ClientStats::new(id)))
+            .collect::<Vec<_>>();
+
+        // Each client sends its share of the target rate.
+        let period = Duration::from_secs_f64(stats.len() as f64 / rate.tps as f64);
+        let in_flight = Arc::new(Semaphore::new(rate.max_in_flight));
+
+        let handles = stats
+            .iter()
+            .map(|stats| {
+                let rpc_client = Arc::new(context.test_validator.get_async_rpc_client());
+                let payer = Keypair::try_from(context.payer.to_bytes().as_slice()).unwrap();
+                let fixture = context.take_fixture();
+
+                let stats = stats.clone();
+                let mix = mix.clone();
+                let in_flight = in_flight.clone();
+                let recorder = recorder.clone();
+                let stop = stop.clone();
+
+                spawn(async move {
+                    start_open_loop_client(
+                        stats, rpc_client, payer, fixture, mix, period, in_flight, recorder, stop,
+                    )
+                    .await
+                })
+            })
+            .collect();
+
+        Self {
+            stop,
+            handles,
+            stats,
+        }
+    }
+
+    /// Live counters of the clients.
+    pub fn stats(&self) -> &[Arc<ClientStats>] {
+        &self.stats
+    }
+
+    /// Signals the clients to stop and waits for them to finish.
+    pub async fn stop(self) {
+        self.stop.cancel();
+
+        for handle in self.handles {
+            let _ = handle.await;
+        }
+    }
+}
+
+/// Sends and confirms a transaction, returning its signature together with
+/// the result, so failed transactions can also be identified.
+pub async fn send_transaction(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+) -> (Signature, Result<Signature, ClientError>) {
+    let (signature, result) = send_and_confirm(rpc_client, instructions, payer, signers).await;
+    (signature, result.map(|_| signature))
+}
+
+/// Simulates a transaction with the instructions, signed with the latest
+/// confirmed blockhash, returning the result of the simulation.
+pub async fn simulate_transaction(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+) -> RpcResult<RpcSimulateTransactionResult> {
+    let (latest_blockhash, _) = rpc_client
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .unwrap();
+    let transaction =
+        Transaction::new_signed_with_payer(instructions, Some(payer), signers, latest_blockhash);
+    rpc_client.simulate_transaction(&transaction).await
+}
+
+/// Simulates the operation, returning the compute units consumed by its
+/// target instruction or, when the simulation fails, the error message.
+pub async fn simulate_units(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    operation: &Operation,
+    fixture: &Fixture,
+) -> Result<u64, String> {
+    let mut signers = vec![payer];
+    signers.extend(operation.signers(fixture));
+
+    let result = simulate_transaction(
+        rpc_client,
+        &operation.instructions,
+        &payer.pubkey(),
+        &signers,
+    )
+    .await
+    .map_err(|error| error.to_string())?
+    .value;
+
+    if let Some(error) = result.err {
+        return Err(format!("{error:?}"));
+    }
+
+    result
+        .logs
+        .as_deref()
+        .map(instruction_units)
+        .and_then(|units| units.get(operation.target).copied())
+        .ok_or_else(|| "missing compute units in the logs".to_string())
+}
+
+/// Returns the compute units consumed by each top-level instruction of a
+/// transaction, parsed from its logs.
+///
+/// Instructions of builtin programs, which do not log the units consumed,
+/// are reported as `0`.
+pub fn instruction_units(logs: &[String]) -> Vec<u64> {
+    let mut units = Vec::new();
+    let mut depth = 0usize;
+
+    for log in logs {
+        // Only lines of the form "Program <id> <event>" are relevant.
+        let Some((program_id, event)) = log
+            .strip_prefix("Program ")
+            .and_then(|message| message.split_once(' '))
+        else {
+            continue;
+        };
+        if program_id.parse::<Pubkey>().is_err() {
+            continue;
+        }
+
+        if event.starts_with("invoke [") {
+            depth += 1;
+            if depth == 1 {
+                units.push(0);
+            }
+        } else if let Some(consumed) = event.strip_prefix("consumed ") {
+            if let (1, Some(last)) = (depth, units.last_mut()) {
+                *last = consumed
+                    .split(' ')
+                    .next()
+                    .and_then(|consumed| consumed.parse().ok())
+                    .unwrap_or_default();
+            }
+        } else if event == "success" || event.starts_with("failed") {
+            depth = depth.saturating_sub(1);
+        }
+    }
+
+    units
+}
+
+// Slot and times of a transaction confirmed without errors.
+struct Confirmed {
+    slot: u64,
+    processed_at: SystemTime,
+    confirmed_at: SystemTime,
+}
+
+// Sends a transaction and polls its signature status until it is confirmed,
+// keeping track of when it was first seen processed.
+async fn send_and_confirm(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+) -> (Signature, Result<Confirmed, ClientError>) {
+    let (latest_blockhash, _) = rpc_client
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .unwrap();
+    let transaction =
+        Transaction::new_signed_with_payer(instructions, Some(payer), signers, latest_blockhash);
+    let signature = transaction.signatures[0];
+
+    if let Err(error) = rpc_client.send_transaction(&transaction).await {
+        return (signature, Err(ClientError::new(&error, instructions)));
+    }
+
+    let sent = Instant::now();
+    let mut processed_at = None;
+
+    loop {
+        sleep(STATUS_POLL_INTERVAL).await;
+
+        let status = rpc_client
+            .get_signature_statuses(&[signature])
+            .await
+            .ok()
+            .and_then(|response| response.value.into_iter().next().flatten());
+
+        if let Some(status) = status {
+            let first_seen = *processed_at.get_or_insert_with(SystemTime::now);
+
+            if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                let result = match status.err {
+                    Some(error) => Err(ClientError::from_transaction_error(
+                        TransactionError::from(error),
+                        instructions,
+                    )),
+                    None => Ok(Confirmed {
+                        slot: status.slot,
+                        processed_at: first_seen,
+                        confirmed_at: SystemTime::now(),
+                    }),
+                };
+                return (signature, result);
+            }
+        }
+
+        if sent.elapsed() >= CONFIRMATION_TIMEOUT {
+            return (signature, Err(ClientError::confirmation_timeout()));
+        }
+    }
+}
+
+/// Runs a closed-loop client, sending one transaction at a time until
+/// `interrupted` is cancelled.
+///
+/// Each transaction is recorded in `recorder` and its outcome in `stats`; the
+/// token accounts of the `fixture` are checked against `invariants`. A new
+/// fixture is created when none is given.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_client(
+    stats: Arc<ClientStats>,
+    rpc_client: Arc<RpcClient>,
+    payer: Keypair,
+    fixture: Option<Fixture>,
+    mix: InstructionMix,
+    recorder: Recorder,
+    invariants: Invariants,
+    interrupted: CancellationToken,
+) {
+    let fixture = match fixture {
+        Some(fixture) => fixture,
+        None => {
+            let fixture = Fixture::default();
+            fixture.create(&rpc_client, &payer).await;
+            fixture
+        }
+    };
+
+    let (finalizer, receiver) = mpsc::unbounded_channel();
+    let finalization = spawn(finalize_records(
+        rpc_client.clone(),
+        receiver,
+        recorder,
+        interrupted.clone(),
+    ));
+
+    let mut state = FixtureState::default();
+    let mut tracker = FixtureTracker::new(stats.id);
+    let mut rng = StdRng::from_entropy();
+
+    while !interrupted.is_cancelled() {
+        let kind = mix.sample(&mut rng);
+        let operation = Operation::new(kind, &fixture, &state, &payer.pubkey());
+
+        let mut signers = vec![&payer];
+        signers.extend(operation.signers(&fixture));
+
+        let sent_at = SystemTime::now();
+        stats.sent.fetch_add(1, Ordering::Relaxed);
+        stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        let sent = interrupted
+            .run_until_cancelled(send_and_confirm(
+                &rpc_client,
+                &operation.instructions,
+                &payer.pubkey(),
+                &signers,
+            ))
+            .await;
+
+        stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        // Transactions interrupted by a shutdown are not recorded.
+        let Some((signature, result)) = sent else {
+            break;
+        };
+
+        let (confirmed, error) = match result {
+            Ok(confirmed) => (Some(confirmed), None),
+            Err(error) => (None, Some(error)),
+        };
+        let slot = match &confirmed {
+            Some(confirmed) => confirmed.slot,
+            None => rpc_client.get_slot().await.unwrap_or_default(),
+        };
+
+        stats.record(
+            error.as_ref(),
+            confirmed
+                .as_ref()
+                .and_then(|confirmed| confirmed.confirmed_at.duration_since(sent_at).ok()),
+        );
+
+        tracker
+            .record(
+                &rpc_client,
+                &fixture,
+                kind,
+                signature,
+                error.as_ref().map(|error| error.class.category()),
+            )
+            .await;
+        tracker.check(&rpc_client, &fixture, &invariants).await;
+
+        match &error {
+            None => state.apply(kind),
+            Some(error) => {
+                // The transaction might have landed after all, so the state
+                // is reloaded from the accounts.
+                if error.class.is_outcome_unknown() {
+                    state.refresh(&rpc_client, &fixture).await;
+                }
+                interrupted
+                    .run_until_cancelled(sleep(Duration::from_millis(200)))
+                    .await;
+            }
+        }
+
+        let _ = finalizer.send(TransactionRecord {
+            client_id: stats.id,
+            instruction: kind,
+            signature,
+            sent_at,
+            processed_at: confirmed.as_ref().map(|confirmed| confirmed.processed_at),
+            confirmed_at: confirmed.as_ref().map(|confirmed| confirmed.confirmed_at),
+            finalized_at: None,
+            slot,
+            error,
+        });
+    }
+
+    drop(finalizer);
+    let _ = finalization.await;
+}
+
+// Transaction sent by an open-loop client, waiting for confirmation.
+struct PendingTransaction {
+    signature: Signature,
+    kind: InstructionKind,
+    instructions: Vec<Instruction>,
+    sent_at: SystemTime,
+    sent: Instant,
+    processed_at: Option<SystemTime>,
+    // Slot of the in-flight limit, released once the outcome is known.
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Sends a transaction every `period` without waiting for confirmations.
+///
+/// Each transaction is sent by its own task, and ticks missed while the
+/// client was busy are sent right away. A send is skipped, and counted as
+/// dropped, while `in_flight` has no permits left. Sent transactions are
+/// handed over to a collector task that polls their signature status until
+/// they are confirmed, and then to a finalizer task that waits for their
+/// finalization.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_open_loop_client(
+    stats: Arc<ClientStats>,
+    rpc_client: Arc<RpcClient>,
+    payer: Keypair,
+    fixture: Option<Fixture>,
+    mix: InstructionMix,
+    period: Duration,
+    in_flight: Arc<Semaphore>,
+    recorder: Recorder,
+    interrupted: CancellationToken,
+) {
+    let fixture = match fixture {
+        Some(fixture) => fixture,
+        None => {
+            let fixture = Fixture::default();
+            fixture.create(&rpc_client, &payer).await;
+            fixture
+        }
+    };
+
+    let Some(latest_blockhash) = interrupted
+        .run_until_cancelled(get_latest_blockhash(&rpc_client))
+        .await
+    else {
+        return;
+    };
+
+    let (finalizer, receiver) = mpsc::unbounded_channel();
+    let finalization = spawn(finalize_records(
+        rpc_client.clone(),
+        receiver,
+        recorder,
+        interrupted.clone(),
+    ));
+
+    // Instructions of the transactions that could not be sent or failed, so
+    // that the fixture state can be reloaded.
+    let (failure_sender, mut failures) = mpsc::unbounded_channel();
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let collector = spawn(collect_statuses(
+        stats.clone(),
+        rpc_client.clone(),
+        receiver,
+        finalizer.clone(),
+        failure_sender.clone(),
+        interrupted.clone(),
+    ));
+
+    // The blockhash is refreshed by its own task, so that fetching it does
+    // not delay the sends.
+    let (blockhash_sender, blockhash) = watch::channel(latest_blockhash);
+    let refresher = spawn(refresh_blockhash(
+        rpc_client.clone(),
+        blockhash_sender,
+        interrupted.clone(),
+    ));
+
+    let mut state = FixtureState::default();
+    let mut rng = StdRng::from_entropy();
+    let mut sequence = 0u32;
+
+    // Ticks missed while signing are sent right away, so that the client
+    // keeps its rate.
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    while interrupted
+        .run_until_cancelled(ticker.tick())
+        .await
+        .is_some()
+    {
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            stats.dropped.fetch_add(1, Ordering::Relaxed);
+            continue;
+        };
+
+        // The state is reloaded when a transaction that should have changed
+        // it did not succeed.
+        let mut stale = false;
+        while let Ok(kind) = failures.try_recv() {
+            stale |= FixtureState::is_changed_by(kind);
+        }
+        if stale {
+            state.refresh(&rpc_client, &fixture).await;
+        }
+
+        let kind = mix.sample(&mut rng);
+        let operation = Operation::new(kind, &fixture, &state, &payer.pubkey());
+
+        let mut signers = vec![&payer];
+        signers.extend(operation.signers(&fixture));
+
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            COMPUTE_UNIT_LIMIT - sequence % UNIQUE_LIMITS,
+        )];
+        instructions.extend(operation.instructions.iter().cloned());
+        sequence = sequence.wrapping_add(1);
+
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &signers,
+            *blockhash.borrow(),
+        );
+
+        // The state is updated optimistically, since the outcome of the
+        // transaction is only known later; it is reloaded if it fails.
+        state.apply(kind);
+
+        // Each transaction is sent by its own task, so a slow send does not
+        // delay the next ones.
+        spawn(send_open_loop_transaction(
+            stats.clone(),
+            rpc_client.clone(),
+            transaction,
+            kind,
+            instructions,
+            permit,
+            sender.clone(),
+            finalizer.clone(),
+            failure_sender.clone(),
+        ));
+    }
+
+    let _ = refresher.await;
+    drop(sender);
+    drop(finalizer);
+    let _ = collector.await;
+    let _ = finalization.await;
+}
+
+// Sends a transaction of an open-loop client, handing it over to the
+// collector once sent; transactions that cannot be sent are recorded as
+// failed right away and reported to `failures`.
+#[allow(clippy::too_many_arguments)]
+async fn send_open_loop_transaction(
+    stats: Arc<ClientStats>,
+    rpc_client: Arc<RpcClient>,
+    transaction: Transaction,
+    kind: InstructionKind,
+    instructions: Vec<Instruction>,
+    permit: OwnedSemaphorePermit,
+    collector: UnboundedSender<PendingTransaction>,
+    finalizer: UnboundedSender<TransactionRecord>,
+    failures: UnboundedSender<InstructionKind>,
+) {
+    let signature = transaction.signatures[0];
+    let sent_at = SystemTime::now();
+    let sent = Instant::now();
+    stats.sent.fetch_add(1, Ordering::Relaxed);
+
+    let result = rpc_client
+        .send_transaction_with_config(
+            &transaction,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..RpcSendTransactionConfig::default()
+            },
+        )
+        .await;
+
+    match result {
+        Ok(_) => {
+            stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+            let _ = collector.send(PendingTransaction {
+                signature,
+                kind,
+                instructions,
+                sent_at,
+                sent,
+                processed_at: None,
+                _permit: permit,
+            });
+        }
+        Err(error) => {
+            let error = ClientError::new(&error, &instructions);
+            stats.record(Some(&error), None);
+            let _ = failures.send(kind);
+
+            let _ = finalizer.send(TransactionRecord {
+                client_id: stats.id,
+                instruction: kind,
+                signature,
+                sent_at,
+                processed_at: None,
+                confirmed_at: None,
+                finalized_at: None,
+                slot: rpc_client.get_slot().await.unwrap_or_default(),
+                error: Some(error),
+            });
+        }
+    }
+}
+
+// Fetches the latest confirmed blockhash, retrying until it succeeds.
+async fn get_latest_blockhash(rpc_client: &RpcClient) -> Hash {
+    loop {
+        if let Ok((hash, _)) = rpc_client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+        {
+            return hash;
+        }
+        sleep(STATUS_POLL_INTERVAL).await;
+    }
+}
+
+// Refreshes the blockhash used by an open-loop client until `interrupted`
+// is cancelled.
+async fn refresh_blockhash(
+    rpc_client: Arc<RpcClient>,
+    blockhash: watch::Sender<Hash>,
+    interrupted: CancellationToken,
+) {
+    while interrupted
+        .run_until_cancelled(sleep(BLOCKHASH_REFRESH_INTERVAL))
+        .await
+        .is_some()
+    {
+        if let Ok((hash, _)) = rpc_client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+        {
+            blockhash.send_replace(hash);
+        }
+    }
+}
+
+// Polls the signature status of the transactions sent by an open-loop
+// client, handing each one over to the finalizer once it is confirmed or
+// timed out; failed transactions are also reported to `failures`.
+//
+// Once `interrupted` is cancelled, the statuses are polled one last time;
+// transactions still in flight are not recorded.
+async fn collect_statuses(
+    stats: Arc<ClientStats>,
+    rpc_client: Arc<RpcClient>,
+    mut receiver: UnboundedReceiver<PendingTransaction>,
+    finalizer: UnboundedSender<TransactionRecord>,
+    failures: UnboundedSender<InstructionKind>,
+    interrupted: CancellationToken,
+) {
+    let mut pending = Vec::new();
+    let mut sending = true;
+
+    while sending || !pending.is_empty() {
+        let cancelled = interrupted
+            .run_until_cancelled(sleep(STATUS_POLL_INTERVAL))
+            .await
+            .is_none();
+
+        loop {
+            match receiver.try_recv() {
+                Ok(transaction) => pending.push(transaction),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    sending = false;
+                    break;
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            pending = resolve_statuses(&stats, &rpc_client, &finalizer, &failures, pending).await;
+        }
+
+        if cancelled {
+            break;
+        }
+    }
+}
+
+// Hands over the transactions with a confirmed status or timed out to the
+// finalizer, returning the ones still in flight.
+async fn resolve_statuses(
+    stats: &ClientStats,
+    rpc_client: &RpcClient,
+    finalizer: &UnboundedSender<TransactionRecord>,
+    failures: &UnboundedSender<InstructionKind>,
+    transactions: Vec<PendingTransaction>,
+) -> Vec<PendingTransaction> {
+    let mut statuses = Vec::with_capacity(transactions.len());
+
+    for chunk in transactions.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+        let signatures = chunk
+            .iter()
+            .map(|transaction| transaction.signature)
+            .collect::<Vec<_>>();
+
+        match rpc_client.get_signature_statuses(&signatures).await {
+            Ok(response) => statuses.extend(response.value),
+            Err(_) => statuses.extend(chunk.iter().map(|_| None)),
+        }
+    }
+
+    let slot = rpc_client.get_slot().await.unwrap_or_default();
+    let mut pending = Vec::new();
+
+    for (mut transaction, status) in transactions.into_iter().zip(statuses) {
+        if status.is_some() {
+            transaction.processed_at.get_or_insert_with(SystemTime::now);
+        }
+
+        let (slot, error) = match status {
+            Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => (
+                status.slot,
+                status.err.map(|error| {
+                    ClientError::from_transaction_error(
+                        TransactionError::from(error),
+                        &transaction.instructions,
+                    )
+                }),
+            ),
+            _ if transaction.sent.elapsed() >= CONFIRMATION_TIMEOUT => {
+                (slot, Some(ClientError::confirmation_timeout()))
+            }
+            _ => {
+                pending.push(transaction);
+                continue;
+            }
+        };
+
+        stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        // Measured when the status is polled, so it can exceed the actual
+        // latency by up to the polling interval.
+        stats.record(error.as_ref(), Some(transaction.sent.elapsed()));
+
+        let success = error.is_none();
+        if !success {
+            let _ = failures.send(transaction.kind);
+        }
+
+        let _ = finalizer.send(TransactionRecord {
+            client_id: stats.id,
+            instruction: transaction.kind,
+            signature: transaction.signature,
+            sent_at: transaction.sent_at,
+            processed_at: transaction.processed_at.filter(|_| success),
+            confirmed_at: success.then(SystemTime::now),
+            finalized_at: None,
+            slot,
+            error,
+        });
+    }
+
+    pending
+}
+
+// Polls the signature status of the confirmed transactions of a client until
+// they are finalized, recording the transactions in the order they were
+// received.
+//
+// Once `interrupted` is cancelled, the remaining transactions are recorded
+// without waiting for their finalization.
+async fn finalize_records(
+    rpc_client: Arc<RpcClient>,
+    mut receiver: UnboundedReceiver<TransactionRecord>,
+    recorder: Recorder,
+    interrupted: CancellationToken,
+) {
+    let mut pending = VecDeque::new();
+
+    while interrupted
+        .run_until_cancelled(sleep(FINALIZATION_POLL_INTERVAL))
+        .await
+        .is_some()
+    {
+        while let Ok(record) = receiver.try_recv() {
+            pending.push_back(record);
+        }
+
+        poll_finalized(&rpc_client, &mut pending).await;
+
+        // Confirmed transactions not finalized in time, e.g. because their
+        // fork was abandoned, are recorded without a finalization time.
+        while pending.front().is_some_and(|record| {
+            !record.is_success()
+                || record.finalized_at.is_some()
+                || record
+                    .sent_at
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed >= CONFIRMATION_TIMEOUT)
+        }) {
+            recorder.record(pending.pop_front().unwrap());
+        }
+    }
+
+    while let Some(record) = receiver.recv().await {
+        pending.push_back(record);
+    }
+    for record in pending {
+        recorder.record(record);
+    }
+}
+
+// Sets the finalization time of the successful transactions with a
+// finalized status.
+async fn poll_finalized(rpc_client: &RpcClient, records: &mut VecDeque<TransactionRecord>) {
+    let mut unfinalized = records
+        .iter_mut()
+        .filter(|record| record.is_success() && record.finalized_at.is_none())
+        .collect::<Vec<_>>();
+
+    for chunk in unfinalized.chunks_mut(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+        let signatures = chunk
+            .iter()
+            .map(|record| record.signature)
+            .collect::<Vec<_>>();
+
+        let Ok(response) = rpc_client.get_signature_statuses(&signatures).await else {
+            continue;
+        };
+
+        for (record, status) in chunk.iter_mut().zip(response.value) {
+            if status
+                .is_some_and(|status| status.satisfies_commitment(CommitmentConfig::finalized()))
+            {
+                record.finalized_at = Some(SystemTime::now());
+            }
+        }
+    }
+}
+
+/// Simulates each instruction of the mix in turn to keep track of the
+/// compute units they consume.
+///
+/// The units of the last successful simulation of each instruction are
+/// stored in `units`.
+pub async fn start_monitor(
+    units: Arc<Mutex<BTreeMap<InstructionKind, u64>>>,
+    rpc_client: RpcClient,
+    payer: Keypair,
+    mix: InstructionMix,
+    interrupted: CancellationToken,
+) {
+    let fixture = Fixture::default();
+    fixture.create(&rpc_client, &payer).await;
+
+    // Simulations do not change the accounts, so the state never changes.
+    let state = FixtureState::default();
+
+    for kind in mix.kinds().iter().cycle() {
+        let operation = Operation::new(*kind, &fixture, &state, &payer.pubkey());
+
+        let Some(result) = interrupted
+            .run_until_cancelled(simulate_units(&rpc_client, &payer, &operation, &fixture))
+            .await
+        else {
+            break;
+        };
+
+        if let Ok(consumed) = result {
+            units.lock().unwrap().insert(*kind, consumed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn units_of_top_level_instructions() {
+        let token = Pubkey::new_unique();
+        let system = Pubkey::new_unique();
+
+        let logs = logs(&[
+            &format!("Program {system} invoke [1]"),
+            &format!("Program {system} success"),
+            &format!("Program {token} invoke [1]"),
+            "Program log: Instruction: Transfer",
+            &format!("Program {system} invoke [2]"),
+            &format!("Program {system} consumed 150 of 1000 compute units"),
+            &format!("Program {system} success"),
+            &format!("Program {token} consumed 4645 of 200000 compute units"),
+            &format!("Program {token} success"),
+        ]);
+
+        assert_eq!(instruction_units(&logs), vec![0, 4645]);
+    }
+
+    #[test]
+    fn units_of_failed_instruction() {
+        let token = Pubkey::new_unique();
+
+        let logs = logs(&[
+            &format!("Program {token} invoke [1]"),
+            &format!("Program {token} consumed 120 of 200000 compute units"),
+            &format!("Program {token} failed: custom program error: 0x1"),
+        ]);
+
+        assert_eq!(instruction_units(&logs), vec![120]);
+    }
+
+    #[test]
+    fn units_without_logs() {
+        assert!(instruction_units(&[]).is_empty());
+    }
+}
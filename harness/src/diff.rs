@@ -0,0 +1,338 @@
+//! Differential execution of token instructions.
+//!
+//! The same transactions are executed on two validators: one running the
+//! original SPL Token program and one where the program was migrated to
+//! p-token. Any difference in the outcome of a transaction – error, logs,
+//! return data or the state of its writable accounts – is reported as a
+//! compatibility issue.
+
+use {
+    crate::{
+        activation::{activate_targets, ActivationOrder},
+        client::send_transaction,
+        report::MigrationTimeline,
+        validator::{ValidatorContext, P_TOKEN_MIGRATION},
+        workload::{Fixture, InstructionKind, Operation},
+    },
+    agave_feature_set::replace_spl_token_with_p_token::SPL_TOKEN_PROGRAM_ID,
+    solana_account_decoder_client_types::UiAccountEncoding,
+    solana_rpc_client_api::{
+        config::{
+            CommitmentConfig, RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+        },
+        response::RpcSimulateTransactionResult,
+    },
+    solana_sdk::{
+        account::Account, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair,
+        signer::Signer, transaction::Transaction,
+    },
+    solana_sdk_ids::bpf_loader_upgradeable,
+    solana_system_interface::instruction::transfer,
+    std::{fmt, io, path::Path},
+    tokio_util::sync::CancellationToken,
+};
+
+/// Lamports transferred to the payer of the compared transactions.
+const PAYER_LAMPORTS: u64 = 1_000 * LAMPORTS_PER_SOL;
+
+/// Difference between the execution of a transaction on SPL Token and
+/// p-token.
+pub struct Divergence {
+    /// Instruction of the compared transaction.
+    pub instruction: InstructionKind,
+    /// What differs, e.g. `error`, `logs` or the address of an account.
+    pub field: String,
+    /// Value on SPL Token.
+    pub spl_token: String,
+    /// Value on p-token.
+    pub p_token: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {}", self.instruction, self.field)?;
+        writeln!(f, "    spl-token: {}", self.spl_token)?;
+        write!(f, "    p-token:   {}", self.p_token)
+    }
+}
+
+/// Pair of validators, one with SPL Token and one with p-token.
+pub struct DiffContext {
+    /// Validator with SPL Token.
+    pub spl_token: ValidatorContext,
+    /// Validator with p-token, already migrated.
+    pub p_token: ValidatorContext,
+    /// Payer of the transactions, funded with the same amount on both
+    /// validators.
+    pub payer: Keypair,
+}
+
+impl DiffContext {
+    /// Starts both validators, with fresh ledgers under `ledger_path`, and
+    /// migrates SPL Token to p-token on one of them.
+    ///
+    /// Fails when a validator cannot be started or the migration fails.
+    pub async fn start(
+        elf_directory: &str,
+        ledger_path: &Path,
+        slots_per_epoch: u64,
+    ) -> io::Result<Self> {
+        let mut contexts = Vec::with_capacity(2);
+
+        for name in ["spl-token", "p-token"] {
+            let ledger_path = ledger_path.join(name);
+            // Both validators must start from the same state.
+            if ledger_path.exists() {
+                std::fs::remove_dir_all(&ledger_path)?;
+            }
+            contexts.push(
+                ValidatorContext::start(
+                    &[P_TOKEN_MIGRATION],
+                    elf_directory,
+                    &ledger_path,
+                    slots_per_epoch,
+                    &[],
+                )
+                .await?,
+            );
+        }
+
+        let p_token = contexts.pop().unwrap();
+        let spl_token = contexts.pop().unwrap();
+
+        activate_targets(
+            &p_token,
+            &[P_TOKEN_MIGRATION],
+            ActivationOrder::Sequential,
+            &mut MigrationTimeline::default(),
+            &CancellationToken::new(),
+        )
+        .await?;
+        p_token
+            .wait_for_program(&SPL_TOKEN_PROGRAM_ID, &bpf_loader_upgradeable::id())
+            .await;
+
+        let payer = Keypair::new();
+
+        for context in [&spl_token, &p_token] {
+            context
+                .send_transaction(
+                    &[transfer(
+                        &context.payer.pubkey(),
+                        &payer.pubkey(),
+                        PAYER_LAMPORTS,
+                    )],
+                    &context.payer.pubkey(),
+                    &[&context.payer],
+                )
+                .await?;
+        }
+
+        Ok(Self {
+            spl_token,
+            p_token,
+            payer,
+        })
+    }
+
+    /// Creates the fixture accounts on both validators.
+    pub async fn create_fixture(&self, fixture: &Fixture) {
+        for context in [&self.spl_token, &self.p_token] {
+            fixture
+                .create(&context.test_validator.get_async_rpc_client(), &self.payer)
+                .await;
+        }
+    }
+
+    /// Executes the operation on both validators, returning the differences
+    /// between them.
+    ///
+    /// The transaction is simulated first to compare its outcome and then
+    /// sent, so both validators move on to the same state.
+    pub async fn execute(
+        &self,
+        operation: &Operation,
+        fixture: &Fixture,
+        ignore_logs: bool,
+    ) -> Vec<Divergence> {
+        let mut signers = vec![&self.payer];
+        signers.extend(operation.signers(fixture));
+
+        // The payer is excluded since its balance is not affected by the
+        // token program.
+        let mut writable = operation
+            .instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .filter(|meta| meta.is_writable && meta.pubkey != self.payer.pubkey())
+            .map(|meta| meta.pubkey)
+            .collect::<Vec<_>>();
+        writable.sort();
+        writable.dedup();
+
+        let spl_token = simulate(&self.spl_token, operation, &signers, &writable).await;
+        let p_token = simulate(&self.p_token, operation, &signers, &writable).await;
+
+        let mut divergences = spl_token.compare(&p_token, ignore_logs);
+
+        let mut results = Vec::with_capacity(2);
+
+        for context in [&self.spl_token, &self.p_token] {
+            let (_, result) = send_transaction(
+                &context.test_validator.get_async_rpc_client(),
+                &operation.instructions,
+                &self.payer.pubkey(),
+                &signers,
+            )
+            .await;
+            results.push(match result {
+                Ok(_) => "success".to_string(),
+                Err(error) => error.class.to_string(),
+            });
+        }
+
+        if results[0] != results[1] {
+            let p_token = results.pop().unwrap();
+            let spl_token = results.pop().unwrap();
+            divergences.push(("result".to_string(), spl_token, p_token));
+        }
+
+        divergences
+            .into_iter()
+            .map(|(field, spl_token, p_token)| Divergence {
+                instruction: operation.kind,
+                field,
+                spl_token,
+                p_token,
+            })
+            .collect()
+    }
+}
+
+/// Outcome of a simulated transaction.
+struct Execution {
+    error: Option<String>,
+    /// Logs without compute unit consumption, which is expected to differ.
+    logs: Vec<String>,
+    return_data: Option<String>,
+    /// State of the writable accounts after the transaction; `None` when
+    /// the account does not exist.
+    accounts: Vec<(Pubkey, Option<Account>)>,
+}
+
+impl Execution {
+    fn new(result: RpcSimulateTransactionResult, writable: &[Pubkey]) -> Self {
+        let accounts = result.accounts.unwrap_or_default();
+
+        Self {
+            error: result.err.map(|error| format!("{error:?}")),
+            logs: result
+                .logs
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|log| !log.contains(" compute units"))
+                .collect(),
+            return_data: result.return_data.map(|data| format!("{data:?}")),
+            accounts: writable
+                .iter()
+                .enumerate()
+                .map(|(i, address)| {
+                    (
+                        *address,
+                        accounts
+                            .get(i)
+                            .cloned()
+                            .flatten()
+                            .and_then(|account| account.decode::<Account>()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    // Returns the differences with another execution, as tuples of the
+    // field name and the value of each execution.
+    fn compare(&self, other: &Execution, ignore_logs: bool) -> Vec<(String, String, String)> {
+        let mut differences = Vec::new();
+
+        if self.error != other.error {
+            differences.push((
+                "error".to_string(),
+                format!("{:?}", self.error),
+                format!("{:?}", other.error),
+            ));
+        }
+
+        if !ignore_logs && self.logs != other.logs {
+            differences.push((
+                "logs".to_string(),
+                self.logs.join(" | "),
+                other.logs.join(" | "),
+            ));
+        }
+
+        if self.return_data != other.return_data {
+            differences.push((
+                "return data".to_string(),
+                format!("{:?}", self.return_data),
+                format!("{:?}", other.return_data),
+            ));
+        }
+
+        for ((address, account), (_, other_account)) in self.accounts.iter().zip(&other.accounts) {
+            if account_state(account) != account_state(other_account) {
+                differences.push((
+                    format!("account {address}"),
+                    format!("{:?}", account_state(account)),
+                    format!("{:?}", account_state(other_account)),
+                ));
+            }
+        }
+
+        differences
+    }
+}
+
+// Returns the lamports, owner and data of an account, leaving out fields
+// not set by the program.
+fn account_state(account: &Option<Account>) -> Option<(u64, &Pubkey, &[u8])> {
+    account
+        .as_ref()
+        .map(|account| (account.lamports, &account.owner, account.data.as_slice()))
+}
+
+async fn simulate(
+    context: &ValidatorContext,
+    operation: &Operation,
+    signers: &[&Keypair],
+    writable: &[Pubkey],
+) -> Execution {
+    let rpc_client = context.test_validator.get_async_rpc_client();
+    let (latest_blockhash, _) = rpc_client
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &operation.instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        latest_blockhash,
+    );
+
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: writable.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    Execution::new(result.value, writable)
+}
@@ -0,0 +1,284 @@
+//! Classification of failed client transactions.
+
+use {
+    solana_rpc_client_api::client_error::{Error, ErrorKind},
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        pubkey::Pubkey,
+        transaction::TransactionError,
+    },
+    std::fmt,
+};
+
+/// Broad category of a failed transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCategory {
+    /// The request did not reach the validator or its response was lost.
+    Transport,
+    /// Transaction-level error.
+    Preflight,
+    /// Error of an instruction.
+    Instruction,
+    /// The transaction was not confirmed before its blockhash expired.
+    ConfirmationTimeout,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorCategory {
+    /// All categories, in reporting order.
+    pub const ALL: [ErrorCategory; 5] = [
+        ErrorCategory::Transport,
+        ErrorCategory::Preflight,
+        ErrorCategory::Instruction,
+        ErrorCategory::ConfirmationTimeout,
+        ErrorCategory::Other,
+    ];
+
+    /// Label of the category, e.g. in reports and metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorCategory::Transport => "transport",
+            ErrorCategory::Preflight => "preflight",
+            ErrorCategory::Instruction => "instruction",
+            ErrorCategory::ConfirmationTimeout => "confirmation-timeout",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+/// Decoded error of a failed transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The request did not reach the validator or its response was lost.
+    Transport,
+    /// Transaction-level error, e.g. `BlockhashNotFound`,
+    /// `ProgramAccountNotFound` or `InvalidProgramForExecution`.
+    Preflight(TransactionError),
+    /// Error of an instruction, with the id of the program it invoked.
+    Instruction {
+        /// Program invoked by the instruction.
+        program_id: Pubkey,
+        /// Error returned by the program.
+        error: InstructionError,
+    },
+    /// The transaction was not confirmed before its blockhash expired.
+    ConfirmationTimeout,
+    /// Any other error reported by the RPC client.
+    Other,
+}
+
+impl ErrorClass {
+    /// Classifies the error returned when sending a transaction with the
+    /// given instructions.
+    ///
+    /// Confirmation timeouts are not reported by the RPC client when sending;
+    /// clients detect them while polling the signature status, see
+    /// [`ClientError::confirmation_timeout`].
+    pub fn classify(error: &Error, instructions: &[Instruction]) -> Self {
+        match error.kind() {
+            ErrorKind::Io(_) | ErrorKind::Reqwest(_) => ErrorClass::Transport,
+            _ => match error.get_transaction_error() {
+                Some(error) => ErrorClass::from_transaction_error(error, instructions),
+                None => ErrorClass::Other,
+            },
+        }
+    }
+
+    /// Classifies the error of a transaction with the given instructions,
+    /// e.g. as reported by its signature status.
+    pub fn from_transaction_error(error: TransactionError, instructions: &[Instruction]) -> Self {
+        match error {
+            TransactionError::InstructionError(index, error) => ErrorClass::Instruction {
+                program_id: instructions
+                    .get(index as usize)
+                    .map(|instruction| instruction.program_id)
+                    .unwrap_or_default(),
+                error,
+            },
+            error => ErrorClass::Preflight(error),
+        }
+    }
+
+    /// Broad category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorClass::Transport => ErrorCategory::Transport,
+            ErrorClass::Preflight(_) => ErrorCategory::Preflight,
+            ErrorClass::Instruction { .. } => ErrorCategory::Instruction,
+            ErrorClass::ConfirmationTimeout => ErrorCategory::ConfirmationTimeout,
+            ErrorClass::Other => ErrorCategory::Other,
+        }
+    }
+
+    /// Indicates whether the transaction might have been executed despite
+    /// the error, e.g. when the response of the validator was lost.
+    pub fn is_outcome_unknown(&self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Transport | ErrorClass::ConfirmationTimeout | ErrorClass::Other
+        )
+    }
+
+    /// Indicates whether the error is expected while the upgraded program is
+    /// in `DelayVisibility` mode, right after the migration.
+    pub fn is_delay_visibility(&self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Preflight(
+                TransactionError::ProgramAccountNotFound
+                    | TransactionError::InvalidProgramForExecution
+            )
+        )
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorClass::Preflight(error) => write!(f, "preflight: {error:?}"),
+            ErrorClass::Instruction { program_id, error } => {
+                write!(f, "instruction: {program_id}: {error:?}")
+            }
+            _ => write!(f, "{}", self.category().label()),
+        }
+    }
+}
+
+/// Error of a failed client transaction.
+#[derive(Clone, Debug)]
+pub struct ClientError {
+    /// Decoded error.
+    pub class: ErrorClass,
+    /// Error message reported by the RPC client.
+    pub message: String,
+}
+
+impl ClientError {
+    /// Error returned when sending a transaction with the given instructions.
+    pub fn new(error: &Error, instructions: &[Instruction]) -> Self {
+        Self {
+            class: ErrorClass::classify(error, instructions),
+            message: error.to_string(),
+        }
+    }
+
+    /// Error of a transaction that landed but failed.
+    pub fn from_transaction_error(error: TransactionError, instructions: &[Instruction]) -> Self {
+        Self {
+            message: error.to_string(),
+            class: ErrorClass::from_transaction_error(error, instructions),
+        }
+    }
+
+    /// Error of a transaction without a confirmed status before its
+    /// blockhash expired.
+    pub fn confirmation_timeout() -> Self {
+        Self {
+            class: ErrorClass::ConfirmationTimeout,
+            message: "transaction not confirmed before its blockhash expired".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_rpc_client_api::request::RpcError, std::io};
+
+    fn instructions() -> Vec<Instruction> {
+        vec![
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+        ]
+    }
+
+    #[test]
+    fn classify_transport() {
+        let error = Error::from(ErrorKind::Io(io::Error::other("connection reset")));
+
+        let class = ErrorClass::classify(&error, &instructions());
+
+        assert_eq!(class, ErrorClass::Transport);
+        assert_eq!(class.category(), ErrorCategory::Transport);
+        assert!(class.is_outcome_unknown());
+    }
+
+    #[test]
+    fn classify_preflight() {
+        let error = Error::from(ErrorKind::TransactionError(
+            TransactionError::InvalidProgramForExecution,
+        ));
+
+        let class = ErrorClass::classify(&error, &instructions());
+
+        assert_eq!(
+            class,
+            ErrorClass::Preflight(TransactionError::InvalidProgramForExecution)
+        );
+        assert!(class.is_delay_visibility());
+
+        let class = ErrorClass::from_transaction_error(
+            TransactionError::BlockhashNotFound,
+            &instructions(),
+        );
+
+        assert_eq!(class.category(), ErrorCategory::Preflight);
+        assert!(!class.is_delay_visibility());
+        assert!(!class.is_outcome_unknown());
+    }
+
+    #[test]
+    fn classify_instruction_error_with_program_id() {
+        let instructions = instructions();
+        let error = Error::from(ErrorKind::TransactionError(
+            TransactionError::InstructionError(1, InstructionError::Custom(4)),
+        ));
+
+        let class = ErrorClass::classify(&error, &instructions);
+
+        assert_eq!(
+            class,
+            ErrorClass::Instruction {
+                program_id: instructions[1].program_id,
+                error: InstructionError::Custom(4),
+            }
+        );
+        assert_eq!(
+            class.to_string(),
+            format!("instruction: {}: Custom(4)", instructions[1].program_id)
+        );
+    }
+
+    #[test]
+    fn classify_instruction_error_out_of_range() {
+        let class = ErrorClass::from_transaction_error(
+            TransactionError::InstructionError(5, InstructionError::InvalidAccountData),
+            &instructions(),
+        );
+
+        assert_eq!(
+            class,
+            ErrorClass::Instruction {
+                program_id: Pubkey::default(),
+                error: InstructionError::InvalidAccountData,
+            }
+        );
+    }
+
+    #[test]
+    fn classify_other() {
+        // Only the timeouts detected by the clients are confirmation timeouts.
+        let error = Error::from(ErrorKind::RpcError(RpcError::ForUser(
+            "unable to confirm transaction".to_string(),
+        )));
+
+        assert_eq!(
+            ErrorClass::classify(&error, &instructions()),
+            ErrorClass::Other
+        );
+        assert_eq!(
+            ClientError::confirmation_timeout().class.category(),
+            ErrorCategory::ConfirmationTimeout
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Module for loading files from local filesystem.
+
+use {
+    serde::Deserialize,
+    solana_account_decoder_client_types::UiAccount,
+    solana_sdk::{account::AccountSharedData, pubkey::Pubkey},
+    std::{
+        fs::{self, File},
+        io::{self, Read},
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+/// Reads program files from a list of directories, searched in order.
+pub struct FileReader {
+    directories: Vec<PathBuf>,
+}
+
+impl FileReader {
+    /// Reader of files in the given directories.
+    pub fn new(directories: &[&str]) -> Self {
+        Self {
+            directories: directories.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    fn find_file(&self, filename: &str) -> Option<PathBuf> {
+        for dir in self.directories.iter() {
+            let candidate = dir.join(filename);
+            if candidate.exists() {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    fn read_file<P: AsRef<Path>>(&self, path: P) -> Vec<u8> {
+        let path = path.as_ref();
+        let mut file = File::open(path).expect("Unable to open file");
+
+        let mut file_data = Vec::new();
+        file.read_to_end(&mut file_data)
+            .expect("Unable to read file");
+        file_data
+    }
+
+    /// Loads the ELF of a program.
+    ///
+    /// Panics when the file does not exist or cannot be read.
+    pub fn load_program_elf(&self, program_name: &str) -> Vec<u8> {
+        let file_name = format!("{program_name}.so");
+        let program_file = self
+            .find_file(&file_name)
+            .unwrap_or_else(|| panic!("Unable to find program ELF file: {file_name}"));
+        self.read_file(program_file)
+    }
+}
+
+/// Account dump in the format of `solana account --output json`.
+#[derive(Deserialize)]
+struct AccountDump {
+    pubkey: String,
+    account: UiAccount,
+}
+
+/// Loads the accounts of JSON dumps, given either as files or directories
+/// containing `.json` files.
+pub fn load_account_dumps(paths: &[PathBuf]) -> io::Result<Vec<(Pubkey, AccountSharedData)>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.retain(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            });
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            let invalid = |message: String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {message}", file.display()),
+                )
+            };
+
+            let data = fs::read(&file).map_err(|e| invalid(e.to_string()))?;
+            let dump: AccountDump =
+                serde_json::from_slice(&data).map_err(|e| invalid(e.to_string()))?;
+
+            let address = Pubkey::from_str(&dump.pubkey)
+                .map_err(|_| invalid(format!("invalid account address: {}", dump.pubkey)))?;
+            let account = dump
+                .account
+                .decode::<AccountSharedData>()
+                .ok_or_else(|| invalid("unsupported account data encoding".to_string()))?;
+
+            Ok((address, account))
+        })
+        .collect()
+}
+
+/// Copies a directory and its regular files, recursively.
+pub fn copy_directory(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
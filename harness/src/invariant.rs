@@ -0,0 +1,446 @@
+//! Token state invariants across the migration.
+//!
+//! Each client tracks the expected state of its fixture accounts from the
+//! transactions it confirmed and periodically compares it with the state of
+//! the accounts on the validator. Any difference is reported as a violation,
+//! together with the transaction that changed the account field.
+
+use {
+    crate::{
+        error::ErrorCategory,
+        workload::{Fixture, InstructionKind, INITIAL_BALANCE, INITIAL_NATIVE_BALANCE},
+    },
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Signature, Signer},
+    },
+    spl_token_interface::state::{Account, AccountState, Mint},
+    std::{
+        fmt,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// Interval between periodic checks of a client.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time after which a transaction with an unknown outcome can no longer
+/// land; longer than the lifetime of its blockhash.
+const UNRESOLVED_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Invariant checks shared between clients.
+#[derive(Clone, Default)]
+pub struct Invariants {
+    violations: Arc<Mutex<Vec<Violation>>>,
+    /// Incremented to request a check from every client.
+    generation: Arc<AtomicU64>,
+    /// Number of checks performed.
+    checks: Arc<AtomicU64>,
+}
+
+impl Invariants {
+    /// Requests every client to check its fixture after its current
+    /// transaction, e.g. right after the migration.
+    pub fn request_check(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Number of checks performed so far.
+    pub fn checks(&self) -> u64 {
+        self.checks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the violations found so far.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.violations.lock().unwrap().clone()
+    }
+}
+
+/// Difference between the expected and actual state of an account.
+#[derive(Clone)]
+pub struct Violation {
+    /// Id of the client of the fixture.
+    pub client_id: u64,
+    /// Account and field that differ, e.g. `source amount`.
+    pub field: &'static str,
+    /// Expected value of the field.
+    pub expected: String,
+    /// Value of the field on the validator.
+    pub actual: String,
+    /// Transaction that changed the field since the previous check, if any.
+    pub signature: Option<Signature>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "client #{:02}: {}: expected {}, found {} (transaction: {})",
+            self.client_id,
+            self.field,
+            self.expected,
+            self.actual,
+            self.signature
+                .map_or("-".to_string(), |signature| signature.to_string())
+        )
+    }
+}
+
+/// Expected state of the fixture accounts.
+#[derive(Clone, PartialEq)]
+struct ExpectedState {
+    supply: u64,
+    source: u64,
+    destination: u64,
+    delegate: Option<Pubkey>,
+    delegated_amount: u64,
+    close_authority: Option<Pubkey>,
+    frozen: bool,
+    /// Token amount of the wrapped SOL account.
+    native: u64,
+}
+
+impl ExpectedState {
+    // State of a fixture right after it was created.
+    fn initial() -> Self {
+        Self {
+            supply: INITIAL_BALANCE,
+            source: INITIAL_BALANCE,
+            destination: 0,
+            delegate: None,
+            delegated_amount: 0,
+            close_authority: None,
+            frozen: false,
+            native: INITIAL_NATIVE_BALANCE,
+        }
+    }
+
+    // Fetches the state of the fixture accounts, together with the slot it
+    // was read at.
+    async fn fetch(rpc_client: &RpcClient, fixture: &Fixture) -> Option<(Self, u64)> {
+        let response = rpc_client
+            .get_multiple_accounts_with_commitment(
+                &[
+                    fixture.mint.pubkey(),
+                    fixture.source.pubkey(),
+                    fixture.destination.pubkey(),
+                    fixture.freezable.pubkey(),
+                    fixture.native.pubkey(),
+                ],
+                rpc_client.commitment(),
+            )
+            .await
+            .ok()?;
+        let (slot, accounts) = (response.context.slot, response.value);
+
+        let [Some(mint), Some(source), Some(destination), Some(freezable), Some(native)] =
+            <[_; 5]>::try_from(accounts).ok()?
+        else {
+            return None;
+        };
+
+        let mint = Mint::unpack(&mint.data).ok()?;
+        let source = Account::unpack(&source.data).ok()?;
+        let destination = Account::unpack(&destination.data).ok()?;
+        let freezable = Account::unpack(&freezable.data).ok()?;
+        let native = Account::unpack(&native.data).ok()?;
+
+        let state = Self {
+            supply: mint.supply,
+            source: source.amount,
+            destination: destination.amount,
+            delegate: source.delegate.into(),
+            delegated_amount: source.delegated_amount,
+            close_authority: source.close_authority.into(),
+            frozen: freezable.state == AccountState::Frozen,
+            native: native.amount,
+        };
+        Some((state, slot))
+    }
+
+    // Fields changed by a transaction with the instruction, as named by
+    // `compare`.
+    fn changed_fields(kind: InstructionKind) -> &'static [&'static str] {
+        match kind {
+            InstructionKind::Transfer | InstructionKind::TransferChecked => &[
+                "source amount",
+                "destination amount",
+                "supply of token accounts",
+            ],
+            InstructionKind::Approve
+            | InstructionKind::ApproveChecked
+            | InstructionKind::Revoke => &["source delegate", "source delegated amount"],
+            InstructionKind::SetAuthority => &["source close authority"],
+            InstructionKind::MintTo
+            | InstructionKind::MintToChecked
+            | InstructionKind::Burn
+            | InstructionKind::BurnChecked => {
+                &["mint supply", "source amount", "supply of token accounts"]
+            }
+            InstructionKind::FreezeAccount | InstructionKind::ThawAccount => &["frozen"],
+            InstructionKind::SyncNative => &["native amount"],
+            _ => &[],
+        }
+    }
+
+    // Updates the state with the effects of a confirmed transaction.
+    fn apply(&mut self, kind: InstructionKind, fixture: &Fixture) {
+        match kind {
+            InstructionKind::Transfer | InstructionKind::TransferChecked => {
+                self.source -= 1;
+                self.destination += 1;
+            }
+            InstructionKind::Approve | InstructionKind::ApproveChecked => {
+                self.delegate = Some(fixture.delegate);
+                self.delegated_amount = 1;
+            }
+            InstructionKind::Revoke => {
+                self.delegate = None;
+                self.delegated_amount = 0;
+            }
+            InstructionKind::SetAuthority => self.close_authority = Some(fixture.owner()),
+            InstructionKind::MintTo | InstructionKind::MintToChecked => {
+                self.supply += 1;
+                self.source += 1;
+            }
+            InstructionKind::Burn | InstructionKind::BurnChecked => {
+                self.supply -= 1;
+                self.source -= 1;
+            }
+            InstructionKind::FreezeAccount => self.frozen = true,
+            InstructionKind::ThawAccount => self.frozen = false,
+            InstructionKind::SyncNative => self.native += 1,
+            // Other instructions only use new accounts or do not change any
+            // account.
+            _ => (),
+        }
+    }
+
+    // Returns the fields that differ from another state.
+    fn compare(&self, actual: &Self) -> Vec<(&'static str, String, String)> {
+        let mut differences = Vec::new();
+
+        let mut check = |field, expected: String, actual: String| {
+            if expected != actual {
+                differences.push((field, expected, actual));
+            }
+        };
+
+        check(
+            "mint supply",
+            self.supply.to_string(),
+            actual.supply.to_string(),
+        );
+        check(
+            "source amount",
+            self.source.to_string(),
+            actual.source.to_string(),
+        );
+        check(
+            "destination amount",
+            self.destination.to_string(),
+            actual.destination.to_string(),
+        );
+        check(
+            "source delegate",
+            format!("{:?}", self.delegate),
+            format!("{:?}", actual.delegate),
+        );
+        check(
+            "source delegated amount",
+            self.delegated_amount.to_string(),
+            actual.delegated_amount.to_string(),
+        );
+        check(
+            "source close authority",
+            format!("{:?}", self.close_authority),
+            format!("{:?}", actual.close_authority),
+        );
+        check("frozen", self.frozen.to_string(), actual.frozen.to_string());
+        check(
+            "native amount",
+            self.native.to_string(),
+            actual.native.to_string(),
+        );
+
+        // Tokens are only moved between the source and destination accounts.
+        check(
+            "supply of token accounts",
+            actual.supply.to_string(),
+            (actual.source + actual.destination).to_string(),
+        );
+
+        differences
+    }
+}
+
+/// Tracks the expected state of a client fixture.
+pub struct FixtureTracker {
+    client_id: u64,
+    expected: ExpectedState,
+    /// Transactions applied to the expected state since the last check.
+    applied: Vec<(Signature, InstructionKind)>,
+    /// Transactions with an unknown outcome that might still land.
+    unresolved: Vec<Unresolved>,
+    last_check: Instant,
+    last_generation: u64,
+}
+
+// Transaction with an unknown outcome, e.g. after a transport error.
+struct Unresolved {
+    signature: Signature,
+    kind: InstructionKind,
+    /// Slot at which the expected state was reloaded after the error, if it
+    /// could be reloaded.
+    reloaded_at: Option<u64>,
+    failed_at: Instant,
+}
+
+impl FixtureTracker {
+    /// Tracker of the fixture of a client, in its initial state.
+    pub fn new(client_id: u64) -> Self {
+        Self {
+            client_id,
+            expected: ExpectedState::initial(),
+            applied: Vec::new(),
+            unresolved: Vec::new(),
+            last_check: Instant::now(),
+            last_generation: 0,
+        }
+    }
+
+    /// Updates the expected state with the outcome of a transaction.
+    ///
+    /// Failed transactions do not change any account, unless the error
+    /// leaves their outcome unknown; in that case the expected state is
+    /// reloaded from the validator and the transaction is applied on top of
+    /// it if it lands later.
+    pub async fn record(
+        &mut self,
+        rpc_client: &RpcClient,
+        fixture: &Fixture,
+        kind: InstructionKind,
+        signature: Signature,
+        error: Option<ErrorCategory>,
+    ) {
+        match error {
+            None => {
+                self.expected.apply(kind, fixture);
+                self.applied.push((signature, kind));
+            }
+            Some(ErrorCategory::Preflight | ErrorCategory::Instruction) => (),
+            Some(_) => {
+                let reloaded_at = match ExpectedState::fetch(rpc_client, fixture).await {
+                    Some((state, slot)) => {
+                        self.expected = state;
+                        Some(slot)
+                    }
+                    None => None,
+                };
+                self.unresolved.push(Unresolved {
+                    signature,
+                    kind,
+                    reloaded_at,
+                    failed_at: Instant::now(),
+                });
+            }
+        }
+    }
+
+    /// Checks the fixture accounts when a periodic check is due or a check
+    /// was requested.
+    pub async fn check(
+        &mut self,
+        rpc_client: &RpcClient,
+        fixture: &Fixture,
+        invariants: &Invariants,
+    ) {
+        let generation = invariants.generation.load(Ordering::SeqCst);
+
+        if self.last_check.elapsed() < CHECK_INTERVAL && self.last_generation == generation {
+            return;
+        }
+        self.last_check = Instant::now();
+        self.last_generation = generation;
+
+        self.resolve(rpc_client, fixture).await;
+
+        let Some((actual, _)) = ExpectedState::fetch(rpc_client, fixture).await else {
+            return;
+        };
+        invariants.checks.fetch_add(1, Ordering::Relaxed);
+
+        let differences = self.expected.compare(&actual);
+        let applied = std::mem::take(&mut self.applied);
+
+        if !differences.is_empty() {
+            let violations = differences
+                .into_iter()
+                .map(|(field, expected, actual)| Violation {
+                    client_id: self.client_id,
+                    field,
+                    expected,
+                    actual,
+                    // The first transaction that changed the field is the
+                    // one that diverged.
+                    signature: applied
+                        .iter()
+                        .find(|(_, kind)| ExpectedState::changed_fields(*kind).contains(&field))
+                        .map(|(signature, _)| *signature),
+                });
+            invariants.violations.lock().unwrap().extend(violations);
+
+            // Continue from the actual state to report new violations only.
+            self.expected = actual;
+        }
+    }
+
+    // Applies the transactions with an unknown outcome that landed after the
+    // expected state was reloaded.
+    async fn resolve(&mut self, rpc_client: &RpcClient, fixture: &Fixture) {
+        if self.unresolved.is_empty() {
+            return;
+        }
+
+        let signatures = self
+            .unresolved
+            .iter()
+            .map(|transaction| transaction.signature)
+            .collect::<Vec<_>>();
+        let Ok(response) = rpc_client
+            .get_signature_statuses_with_history(&signatures)
+            .await
+        else {
+            return;
+        };
+
+        for (transaction, status) in std::mem::take(&mut self.unresolved)
+            .into_iter()
+            .zip(response.value)
+        {
+            match status {
+                Some(status) => {
+                    let landed_after_reload = transaction
+                        .reloaded_at
+                        .is_none_or(|reloaded_at| status.slot > reloaded_at);
+
+                    if status.err.is_none() && landed_after_reload {
+                        self.expected.apply(transaction.kind, fixture);
+                        self.applied.push((transaction.signature, transaction.kind));
+                    }
+                }
+                None if transaction.failed_at.elapsed() < UNRESOLVED_TIMEOUT => {
+                    self.unresolved.push(transaction);
+                }
+                // The transaction expired without landing.
+                None => (),
+            }
+        }
+    }
+}
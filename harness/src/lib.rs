@@ -0,0 +1,96 @@
+//! Harness to simulate Core BPF migrations on a local test validator, such
+//! as the migration of SPL Token to p-token.
+//!
+//! A simulation goes through the following steps:
+//!
+//! 1. [`ValidatorContext::start`] starts a test validator with the
+//!    [`MigrationTarget`]s staged: the new program of each target is stored
+//!    in a buffer account and its feature account is owned by the activator
+//!    program, so it can be activated without the feature keypair.
+//!    [`ValidatorContext::start_from_snapshot`] starts it from a snapshot
+//!    instead.
+//! 2. [`Workload::spawn`] starts clients sending token instructions of an
+//!    [`InstructionMix`](workload::InstructionMix) until stopped, recording
+//!    every transaction in a [`Recorder`].
+//! 3. [`activate_targets`] activates the features of the targets and waits
+//!    for the migration of their programs at the next epoch boundary.
+//! 4. [`finish_run`](report::finish_run) summarizes the recorded
+//!    transactions around the [`MigrationTimeline`] in a [`RunSummary`], and
+//!    [`verify_targets`](verify::verify_targets) checks the accounts of the
+//!    migrated programs.
+//!
+//! For example:
+//!
+//! ```no_run
+//! use {
+//!     p_token_migration_harness::{
+//!         invariant::Invariants, validator::LEDGER_PATH, workload::InstructionMix,
+//!         MigrationTimeline, Recorder, RunSummary, ValidatorContext, Workload,
+//!         P_TOKEN_MIGRATION,
+//!     },
+//!     solana_rpc_client_api::config::CommitmentConfig,
+//!     std::path::Path,
+//!     tokio_util::sync::CancellationToken,
+//! };
+//!
+//! # async fn simulate() {
+//! let context = ValidatorContext::start(
+//!     &[P_TOKEN_MIGRATION],
+//!     "./target/elfs",
+//!     Path::new(LEDGER_PATH),
+//!     200,
+//!     &[],
+//! )
+//! .await
+//! .unwrap();
+//!
+//! let recorder = Recorder::default();
+//! let workload = Workload::spawn(
+//!     &context,
+//!     1..5,
+//!     &InstructionMix::default(),
+//!     recorder.clone(),
+//!     Invariants::default(),
+//!     &CancellationToken::new(),
+//! );
+//!
+//! let feature_id = P_TOKEN_MIGRATION.feature_id;
+//! let activation_slot = context.activate_feature(&feature_id).await.unwrap();
+//! let migration_slot = context.wait_until_feature_active(&feature_id).await;
+//!
+//! context
+//!     .wait_until_slot(migration_slot + 100, CommitmentConfig::confirmed())
+//!     .await;
+//! workload.stop().await;
+//!
+//! let mut timeline = MigrationTimeline::default();
+//! timeline.record_activation(P_TOKEN_MIGRATION.name, activation_slot);
+//! timeline.record_migration(P_TOKEN_MIGRATION.name, migration_slot);
+//! let summary = RunSummary::new(&recorder.take(), &timeline);
+//!
+//! assert_eq!(summary.unexpected_errors, 0);
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod activation;
+pub mod bench;
+pub mod client;
+pub mod diff;
+pub mod error;
+pub mod file;
+pub mod invariant;
+pub mod report;
+pub mod scenario;
+pub mod snapshot;
+pub mod validator;
+pub mod verify;
+pub mod workload;
+
+pub use {
+    activation::{activate_targets, ActivationOrder},
+    client::{OpenLoop, Workload},
+    report::{MigrationTimeline, Recorder, RunSummary},
+    validator::{MigrationTarget, ValidatorContext, P_TOKEN_MIGRATION},
+};
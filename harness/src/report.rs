@@ -0,0 +1,931 @@
+//! Transaction records and run summary.
+
+use {
+    crate::{
+        error::{ClientError, ErrorCategory},
+        workload::InstructionKind,
+    },
+    serde::Serialize,
+    solana_sdk::signature::Signature,
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt,
+        fs::File,
+        io::{BufWriter, Result, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Outcome of a client transaction.
+pub struct TransactionRecord {
+    /// Id of the client that sent the transaction.
+    pub client_id: u64,
+    /// Token instruction sent in the transaction.
+    pub instruction: InstructionKind,
+    /// Signature of the transaction.
+    pub signature: Signature,
+    /// Time when the transaction was sent.
+    pub sent_at: SystemTime,
+    /// Time when the transaction was first seen processed, if it succeeded.
+    pub processed_at: Option<SystemTime>,
+    /// Time when the transaction was confirmed, if it succeeded.
+    pub confirmed_at: Option<SystemTime>,
+    /// Time when the transaction was finalized, if it succeeded and was
+    /// finalized before the client stopped.
+    pub finalized_at: Option<SystemTime>,
+    /// Slot in which the transaction was processed or, for failed
+    /// transactions, the slot observed when the failure was reported.
+    pub slot: u64,
+    /// Error of failed transactions.
+    pub error: Option<ClientError>,
+}
+
+impl TransactionRecord {
+    /// Indicates whether the transaction succeeded.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Time from sending the transaction to reaching the given stage.
+    pub fn latency(&self, stage: LatencyStage) -> Option<Duration> {
+        let reached_at = match stage {
+            LatencyStage::Processed => self.processed_at,
+            LatencyStage::Confirmed => self.confirmed_at,
+            LatencyStage::Finalized => self.finalized_at,
+        }?;
+        reached_at.duration_since(self.sent_at).ok()
+    }
+}
+
+/// Collects the transaction records of a run.
+///
+/// The recorder can be cloned and shared between clients.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    records: Arc<Mutex<Vec<TransactionRecord>>>,
+}
+
+impl Recorder {
+    /// Adds the record of a transaction.
+    pub fn record(&self, record: TransactionRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// Returns the records collected so far, leaving the recorder empty.
+    pub fn take(&self) -> Vec<TransactionRecord> {
+        std::mem::take(&mut *self.records.lock().unwrap())
+    }
+}
+
+/// Slots of the migration events of a run.
+///
+/// The first activation and migration of all targets delimit the phases of
+/// the transactions; the slots of each target are kept in `targets`.
+#[derive(Clone, Default)]
+pub struct MigrationTimeline {
+    /// Slot in which the first feature activation transaction was processed.
+    pub activation_slot: Option<u64>,
+    /// First slot of the epoch in which the first migration took place.
+    pub migration_slot: Option<u64>,
+    /// Slots of the migration events of each target, by target name.
+    pub targets: BTreeMap<String, TargetTimeline>,
+}
+
+/// Slots of the migration events of a single target.
+#[derive(Clone, Copy, Default)]
+pub struct TargetTimeline {
+    /// Slot in which the feature activation transaction was processed.
+    pub activation_slot: Option<u64>,
+    /// First slot of the epoch in which the target was migrated.
+    pub migration_slot: Option<u64>,
+}
+
+impl MigrationTimeline {
+    /// Timeline of a run where the program was already upgraded.
+    pub fn upgraded() -> Self {
+        Self {
+            activation_slot: None,
+            migration_slot: Some(0),
+            targets: BTreeMap::new(),
+        }
+    }
+
+    /// Records the activation of the feature of a target.
+    pub fn record_activation(&mut self, target: &str, slot: u64) {
+        self.activation_slot.get_or_insert(slot);
+        self.targets
+            .entry(target.to_string())
+            .or_default()
+            .activation_slot = Some(slot);
+    }
+
+    /// Forgets the activation of a target whose pending feature was revoked;
+    /// the first activation is then the one of the other targets.
+    pub fn record_revocation(&mut self, target: &str) {
+        self.targets.remove(target);
+        self.activation_slot = self
+            .targets
+            .values()
+            .filter_map(|timeline| timeline.activation_slot)
+            .min();
+    }
+
+    /// Records the migration of a target.
+    pub fn record_migration(&mut self, target: &str, slot: u64) {
+        self.migration_slot.get_or_insert(slot);
+        self.targets
+            .entry(target.to_string())
+            .or_default()
+            .migration_slot = Some(slot);
+    }
+
+    /// Slot in which the target was migrated, if it was migrated.
+    pub fn target_migration_slot(&self, target: &str) -> Option<u64> {
+        self.targets
+            .get(target)
+            .and_then(|timeline| timeline.migration_slot)
+    }
+
+    /// Indicates whether the feature was activated at the given slot; always
+    /// `false` when the run did not activate it.
+    pub fn is_activated(&self, slot: u64) -> bool {
+        self.activation_slot
+            .is_some_and(|activation_slot| slot >= activation_slot)
+    }
+
+    /// Indicates whether the program was upgraded at the given slot.
+    pub fn is_upgraded(&self, slot: u64) -> bool {
+        self.migration_slot
+            .is_some_and(|migration_slot| slot >= migration_slot)
+    }
+
+    /// Phase of the migration at the given slot.
+    pub fn phase(&self, slot: u64) -> MigrationPhase {
+        match (self.activation_slot, self.migration_slot) {
+            (_, Some(migration_slot)) if slot == migration_slot => MigrationPhase::MigrationSlot,
+            (_, Some(migration_slot)) if slot > migration_slot => MigrationPhase::PostMigration,
+            (Some(activation_slot), _) if slot >= activation_slot => {
+                MigrationPhase::ActivationEpoch
+            }
+            _ => MigrationPhase::PreActivation,
+        }
+    }
+}
+
+/// Phase of the migration in which a transaction was processed.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum MigrationPhase {
+    /// Before the feature activation.
+    PreActivation,
+    /// From the feature activation to the end of its epoch.
+    ActivationEpoch,
+    /// First slot of the epoch in which the migration took place.
+    MigrationSlot,
+    /// After the migration slot.
+    PostMigration,
+}
+
+impl MigrationPhase {
+    /// All phases, in chronological order.
+    pub const ALL: [Self; 4] = [
+        Self::PreActivation,
+        Self::ActivationEpoch,
+        Self::MigrationSlot,
+        Self::PostMigration,
+    ];
+
+    /// Label of the phase, e.g. in reports.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PreActivation => "pre-activation",
+            Self::ActivationEpoch => "activation-epoch",
+            Self::MigrationSlot => "migration-slot",
+            Self::PostMigration => "post-migration",
+        }
+    }
+}
+
+/// Commitment level reached by a transaction after it was sent.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum LatencyStage {
+    /// Processed by the validator.
+    Processed,
+    /// Confirmed by the cluster.
+    Confirmed,
+    /// Finalized, i.e. rooted.
+    Finalized,
+}
+
+impl LatencyStage {
+    /// All stages, in the order they are reached.
+    pub const ALL: [Self; 3] = [Self::Processed, Self::Confirmed, Self::Finalized];
+
+    /// Label of the stage, e.g. in reports.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Processed => "processed",
+            Self::Confirmed => "confirmed",
+            Self::Finalized => "finalized",
+        }
+    }
+}
+
+/// Percentiles of a set of latencies.
+pub struct Percentiles {
+    /// Number of latencies.
+    pub count: u64,
+    /// Median latency.
+    pub p50: Duration,
+    /// 90th percentile.
+    pub p90: Duration,
+    /// 99th percentile.
+    pub p99: Duration,
+    /// Maximum latency.
+    pub max: Duration,
+}
+
+impl Percentiles {
+    /// Computes the percentiles with the nearest-rank method, or `None` if
+    /// there are no latencies.
+    pub fn new(mut latencies: Vec<Duration>) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort_unstable();
+
+        let rank = |percentile: usize| {
+            let index = (percentile * latencies.len()).div_ceil(100);
+            latencies[index.saturating_sub(1)]
+        };
+
+        Some(Self {
+            count: latencies.len() as u64,
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: latencies[latencies.len() - 1],
+        })
+    }
+}
+
+/// Latencies of the successful transactions, by migration phase and
+/// stage.
+#[derive(Default)]
+pub struct LatencySummary {
+    /// Percentiles of the phases and stages with at least one latency.
+    pub percentiles: BTreeMap<(MigrationPhase, LatencyStage), Percentiles>,
+}
+
+impl LatencySummary {
+    /// Summarizes the latencies of the records by phase of the `timeline`.
+    pub fn new(records: &[TransactionRecord], timeline: &MigrationTimeline) -> Self {
+        let mut latencies = BTreeMap::<_, Vec<_>>::new();
+
+        for record in records.iter().filter(|r| r.is_success()) {
+            let phase = timeline.phase(record.slot);
+
+            for stage in LatencyStage::ALL {
+                if let Some(latency) = record.latency(stage) {
+                    latencies.entry((phase, stage)).or_default().push(latency);
+                }
+            }
+        }
+
+        Self {
+            percentiles: latencies
+                .into_iter()
+                .filter_map(|(key, latencies)| Some((key, Percentiles::new(latencies)?)))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Latency (ms):")?;
+
+        if self.percentiles.is_empty() {
+            return write!(f, " -");
+        }
+
+        write!(
+            f,
+            "\n  {:<18} {:<10} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "phase", "stage", "count", "p50", "p90", "p99", "max"
+        )?;
+        for ((phase, stage), percentiles) in &self.percentiles {
+            write!(
+                f,
+                "\n  {:<18} {:<10} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                phase.label(),
+                stage.label(),
+                percentiles.count,
+                percentiles.p50.as_millis(),
+                percentiles.p90.as_millis(),
+                percentiles.p99.as_millis(),
+                percentiles.max.as_millis()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Row of the transaction report.
+#[derive(Serialize)]
+struct ReportRow<'a> {
+    client_id: u64,
+    instruction: &'static str,
+    signature: String,
+    /// Unix timestamp in milliseconds.
+    sent_at: u64,
+    /// Unix timestamp in milliseconds.
+    processed_at: Option<u64>,
+    /// Unix timestamp in milliseconds.
+    confirmed_at: Option<u64>,
+    /// Unix timestamp in milliseconds.
+    finalized_at: Option<u64>,
+    slot: u64,
+    phase: &'static str,
+    result: &'static str,
+    error_category: Option<&'static str>,
+    error_class: Option<String>,
+    error: Option<&'a str>,
+    upgraded: bool,
+}
+
+impl<'a> ReportRow<'a> {
+    fn new(record: &'a TransactionRecord, timeline: &MigrationTimeline) -> Self {
+        Self {
+            client_id: record.client_id,
+            instruction: record.instruction.name(),
+            signature: record.signature.to_string(),
+            sent_at: unix_millis(record.sent_at),
+            processed_at: record.processed_at.map(unix_millis),
+            confirmed_at: record.confirmed_at.map(unix_millis),
+            finalized_at: record.finalized_at.map(unix_millis),
+            slot: record.slot,
+            phase: timeline.phase(record.slot).label(),
+            result: if record.is_success() {
+                "success"
+            } else {
+                "error"
+            },
+            error_category: record.error.as_ref().map(|e| e.class.category().label()),
+            error_class: record.error.as_ref().map(|e| e.class.to_string()),
+            error: record.error.as_ref().map(|e| e.message.as_str()),
+            upgraded: timeline.is_upgraded(record.slot),
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Writes the records as JSON Lines, one object per transaction.
+pub fn write_json_lines<W: Write>(
+    writer: &mut W,
+    records: &[TransactionRecord],
+    timeline: &MigrationTimeline,
+) -> Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut *writer, &ReportRow::new(record, timeline))?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Writes the records as CSV, with a header row.
+pub fn write_csv<W: Write>(
+    writer: W,
+    records: &[TransactionRecord],
+    timeline: &MigrationTimeline,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for record in records {
+        writer.serialize(ReportRow::new(record, timeline))?;
+    }
+    writer.flush()
+}
+
+/// Format of the transaction report.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReportFormat {
+    /// JSON Lines, one object per transaction.
+    #[default]
+    JsonLines,
+    /// CSV with a header row.
+    Csv,
+}
+
+/// Takes the records of the run from the `recorder` and summarizes them
+/// around the `timeline`.
+///
+/// When a `report` path is given, the records are first written to it in
+/// the given format. Fails when the report cannot be written.
+pub fn finish_run(
+    recorder: &Recorder,
+    timeline: &MigrationTimeline,
+    report: Option<(&Path, ReportFormat)>,
+) -> Result<RunSummary> {
+    let records = recorder.take();
+
+    if let Some((path, format)) = report {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        match format {
+            ReportFormat::JsonLines => write_json_lines(&mut writer, &records, timeline)?,
+            ReportFormat::Csv => write_csv(writer, &records, timeline)?,
+        }
+    }
+
+    Ok(RunSummary::new(&records, timeline))
+}
+
+/// Outage window of the migration.
+///
+/// The outage spans from the first failed transaction after the feature
+/// activation to the first successful transaction after the migration, so
+/// unrelated failures later in the run do not extend it. Runs without an
+/// activation have no outage.
+#[derive(Default)]
+pub struct Outage {
+    /// Slot in which the feature activation transaction was processed.
+    pub activation_slot: Option<u64>,
+    /// First slot of the epoch in which the migration took place.
+    pub migration_slot: Option<u64>,
+    /// Slot of the first failed transaction after the activation.
+    pub first_failed_slot: Option<u64>,
+    /// Slot of the last failed transaction of the outage.
+    pub last_failed_slot: Option<u64>,
+    /// Slot of the first successful transaction after the migration.
+    pub first_success_slot: Option<u64>,
+    /// Duration of the outage in slots.
+    pub slots: u64,
+    /// Wall-clock duration of the outage, from the time the first failed
+    /// transaction was sent to the time the first successful transaction
+    /// was confirmed.
+    pub duration: Duration,
+}
+
+impl Outage {
+    /// Finds the outage window of the records around the `timeline`.
+    pub fn new(records: &[TransactionRecord], timeline: &MigrationTimeline) -> Self {
+        let Some(activation_slot) = timeline.activation_slot else {
+            return Self {
+                migration_slot: timeline.migration_slot,
+                ..Self::default()
+            };
+        };
+
+        let first_success = records
+            .iter()
+            .filter(|r| r.is_success() && timeline.is_upgraded(r.slot))
+            .min_by_key(|r| (r.slot, r.confirmed_at));
+
+        let failed = records.iter().filter(|r| {
+            !r.is_success()
+                && r.slot >= activation_slot
+                && first_success.is_none_or(|success| r.slot < success.slot)
+        });
+        let first_failed = failed.clone().min_by_key(|r| (r.slot, r.sent_at));
+        let last_failed = failed.max_by_key(|r| (r.slot, r.sent_at));
+
+        let slots = match (first_failed, last_failed) {
+            (Some(first), Some(last)) => last.slot - first.slot + 1,
+            _ => 0,
+        };
+
+        let end = first_success
+            .and_then(|r| r.confirmed_at)
+            .or(last_failed.map(|r| r.sent_at));
+        let duration = match (first_failed, end) {
+            (Some(first), Some(end)) => end.duration_since(first.sent_at).unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+
+        Self {
+            activation_slot: Some(activation_slot),
+            migration_slot: timeline.migration_slot,
+            first_failed_slot: first_failed.map(|r| r.slot),
+            last_failed_slot: last_failed.map(|r| r.slot),
+            first_success_slot: first_success.map(|r| r.slot),
+            slots,
+            duration,
+        }
+    }
+}
+
+impl fmt::Display for Outage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slot = |slot: Option<u64>| slot.map_or("-".to_string(), |s| s.to_string());
+
+        writeln!(f, "Activation slot: {}", slot(self.activation_slot))?;
+        writeln!(f, "Migration slot: {}", slot(self.migration_slot))?;
+        writeln!(f, "First failed slot: {}", slot(self.first_failed_slot))?;
+        writeln!(f, "Last failed slot: {}", slot(self.last_failed_slot))?;
+        writeln!(
+            f,
+            "First successful post-upgrade slot: {}",
+            slot(self.first_success_slot)
+        )?;
+        write!(
+            f,
+            "Outage: {} slot(s) ({} ms)",
+            self.slots,
+            self.duration.as_millis()
+        )
+    }
+}
+
+/// Summary of the transactions of a run.
+pub struct RunSummary {
+    /// Number of transactions.
+    pub total: u64,
+    /// Number of successful transactions.
+    pub success: u64,
+    /// Number of failed transactions.
+    pub error: u64,
+    /// Maximum number of consecutive failed transactions of a single client.
+    pub max_consecutive_failures: u64,
+    /// Outage window of the migration.
+    pub outage: Outage,
+    /// Success rate (in percent) of transactions after the upgrade.
+    pub post_upgrade_success_rate: Option<f64>,
+    /// Number of failed transactions per error category.
+    pub errors_by_category: BTreeMap<ErrorCategory, u64>,
+    /// Number of failed transactions per error class.
+    pub errors_by_class: BTreeMap<String, u64>,
+    /// Number of failed transactions after the activation whose error is
+    /// not expected from the program being in `DelayVisibility` mode; `0`
+    /// when the run did not activate the feature.
+    pub unexpected_errors: u64,
+    /// Latencies of the successful transactions, by migration phase.
+    pub latency: LatencySummary,
+    /// Slots of the migration events of each target, by target name.
+    pub migrations: BTreeMap<String, TargetTimeline>,
+}
+
+impl RunSummary {
+    /// Summarizes the records around the `timeline`.
+    pub fn new(records: &[TransactionRecord], timeline: &MigrationTimeline) -> Self {
+        let success = records.iter().filter(|r| r.is_success()).count() as u64;
+
+        let mut consecutive = HashMap::new();
+        let mut max_consecutive_failures = 0;
+
+        for record in records {
+            let count = consecutive.entry(record.client_id).or_insert(0u64);
+            if record.is_success() {
+                *count = 0;
+            } else {
+                *count += 1;
+                max_consecutive_failures = max_consecutive_failures.max(*count);
+            }
+        }
+
+        let outage = Outage::new(records, timeline);
+
+        let (upgraded_success, upgraded_total) = records
+            .iter()
+            .filter(|r| timeline.is_upgraded(r.slot))
+            .fold((0, 0), |(success, total), r| {
+                (success + r.is_success() as u64, total + 1)
+            });
+        let post_upgrade_success_rate =
+            (upgraded_total > 0).then(|| upgraded_success as f64 * 100.0 / upgraded_total as f64);
+
+        let mut errors_by_category = BTreeMap::new();
+        let mut errors_by_class = BTreeMap::new();
+        let mut unexpected_errors = 0;
+
+        for (record, error) in records
+            .iter()
+            .filter_map(|r| r.error.as_ref().map(|e| (r, e)))
+        {
+            *errors_by_category
+                .entry(error.class.category())
+                .or_default() += 1;
+            *errors_by_class.entry(error.class.to_string()).or_default() += 1;
+
+            if timeline.is_activated(record.slot) && !error.class.is_delay_visibility() {
+                unexpected_errors += 1;
+            }
+        }
+
+        Self {
+            total: records.len() as u64,
+            success,
+            error: records.len() as u64 - success,
+            max_consecutive_failures,
+            outage,
+            post_upgrade_success_rate,
+            errors_by_category,
+            errors_by_class,
+            unexpected_errors,
+            latency: LatencySummary::new(records, timeline),
+            migrations: timeline.targets.clone(),
+        }
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Transactions: {} (✅ {} ❌ {})",
+            self.total, self.success, self.error
+        )?;
+        writeln!(
+            f,
+            "Max consecutive failures: {}",
+            self.max_consecutive_failures
+        )?;
+        if !self.migrations.is_empty() {
+            let slot = |slot: Option<u64>| slot.map_or("-".to_string(), |s| s.to_string());
+
+            write!(f, "Migrations:")?;
+            for (target, timeline) in &self.migrations {
+                write!(
+                    f,
+                    "\n  {target}: activated at slot {}, migrated at slot {}",
+                    slot(timeline.activation_slot),
+                    slot(timeline.migration_slot)
+                )?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", self.outage)?;
+        match self.post_upgrade_success_rate {
+            Some(rate) => writeln!(f, "Post-upgrade success rate: {rate:.2}%")?,
+            None => writeln!(f, "Post-upgrade success rate: -")?,
+        }
+        writeln!(
+            f,
+            "Unexpected errors after activation: {}",
+            self.unexpected_errors
+        )?;
+        writeln!(f, "{}", self.latency)?;
+        write!(f, "Errors by category:")?;
+        for category in ErrorCategory::ALL {
+            let count = self.errors_by_category.get(&category).unwrap_or(&0);
+            write!(f, "\n  {}: {count}", category.label())?;
+        }
+        for (class, count) in &self.errors_by_class {
+            write!(f, "\n    {class}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Thresholds for a run to be considered successful.
+#[derive(Default)]
+pub struct Thresholds {
+    /// Maximum number of consecutive failed transactions of a client.
+    pub max_consecutive_failures: Option<u64>,
+    /// Maximum duration of the outage, in slots.
+    pub max_outage_slots: Option<u64>,
+    /// Minimum success rate (in percent) of transactions after the upgrade.
+    pub min_success_rate: Option<f64>,
+}
+
+impl Thresholds {
+    /// Checks the summary against the thresholds, returning a description
+    /// of each broken threshold.
+    pub fn check(&self, summary: &RunSummary) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if let Some(max) = self.max_consecutive_failures {
+            if summary.max_consecutive_failures > max {
+                violations.push(format!(
+                    "consecutive failures: {} (max {max})",
+                    summary.max_consecutive_failures
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_outage_slots {
+            if summary.outage.slots > max {
+                violations.push(format!(
+                    "outage: {} slot(s) (max {max})",
+                    summary.outage.slots
+                ));
+            }
+        }
+
+        if let Some(min) = self.min_success_rate {
+            match summary.post_upgrade_success_rate {
+                Some(rate) if rate < min => violations.push(format!(
+                    "post-upgrade success rate: {rate:.2}% (min {min:.2}%)"
+                )),
+                None => violations.push("no post-upgrade transactions".to_string()),
+                _ => (),
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::error::ErrorClass,
+        solana_sdk::{
+            instruction::InstructionError, pubkey::Pubkey, transaction::TransactionError,
+        },
+    };
+
+    fn millis(values: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        values.into_iter().map(Duration::from_millis).collect()
+    }
+
+    fn timeline(activation_slot: Option<u64>, migration_slot: Option<u64>) -> MigrationTimeline {
+        MigrationTimeline {
+            activation_slot,
+            migration_slot,
+            ..MigrationTimeline::default()
+        }
+    }
+
+    // Record of a transaction sent in the slot, confirmed 100ms later when
+    // it succeeded.
+    fn record(client_id: u64, slot: u64, error: Option<ErrorClass>) -> TransactionRecord {
+        let sent_at = UNIX_EPOCH + Duration::from_millis(slot * 400);
+        let confirmed_at = error
+            .is_none()
+            .then(|| sent_at + Duration::from_millis(100));
+
+        TransactionRecord {
+            client_id,
+            instruction: InstructionKind::Transfer,
+            signature: Signature::new_unique(),
+            sent_at,
+            processed_at: confirmed_at,
+            confirmed_at,
+            finalized_at: None,
+            slot,
+            error: error.map(|class| ClientError {
+                class,
+                message: String::new(),
+            }),
+        }
+    }
+
+    fn migration_records() -> Vec<TransactionRecord> {
+        let not_found = ErrorClass::Preflight(TransactionError::ProgramAccountNotFound);
+        let invalid_program = ErrorClass::Preflight(TransactionError::InvalidProgramForExecution);
+        let instruction = ErrorClass::Instruction {
+            program_id: Pubkey::new_unique(),
+            error: InstructionError::InsufficientFunds,
+        };
+
+        vec![
+            record(1, 90, None),
+            record(2, 95, Some(ErrorClass::Transport)),
+            record(1, 120, Some(not_found)),
+            record(1, 150, Some(invalid_program)),
+            record(1, 151, None),
+            record(2, 152, None),
+            record(1, 200, Some(instruction)),
+        ]
+    }
+
+    #[test]
+    fn timeline_after_revocation() {
+        let mut timeline = MigrationTimeline::default();
+        timeline.record_activation("first", 100);
+        timeline.record_activation("second", 120);
+
+        timeline.record_revocation("first");
+        assert_eq!(timeline.activation_slot, Some(120));
+        assert!(!timeline.targets.contains_key("first"));
+
+        timeline.record_revocation("second");
+        assert_eq!(timeline.activation_slot, None);
+        assert!(!timeline.is_activated(150));
+    }
+
+    #[test]
+    fn summary_of_no_records() {
+        let summary = RunSummary::new(&[], &timeline(Some(100), Some(150)));
+
+        assert_eq!(summary.total, 0);
+        assert_eq!(summary.success, 0);
+        assert_eq!(summary.error, 0);
+        assert_eq!(summary.max_consecutive_failures, 0);
+        assert_eq!(summary.outage.slots, 0);
+        assert_eq!(summary.post_upgrade_success_rate, None);
+        assert_eq!(summary.unexpected_errors, 0);
+        assert!(summary.latency.percentiles.is_empty());
+    }
+
+    #[test]
+    fn summary_of_a_migration() {
+        let summary = RunSummary::new(&migration_records(), &timeline(Some(100), Some(150)));
+
+        assert_eq!(summary.total, 7);
+        assert_eq!(summary.success, 3);
+        assert_eq!(summary.error, 4);
+        assert_eq!(summary.max_consecutive_failures, 2);
+
+        // The failure after the first post-migration success is not part of
+        // the outage.
+        assert_eq!(summary.outage.first_failed_slot, Some(120));
+        assert_eq!(summary.outage.last_failed_slot, Some(150));
+        assert_eq!(summary.outage.first_success_slot, Some(151));
+        assert_eq!(summary.outage.slots, 31);
+
+        assert_eq!(summary.post_upgrade_success_rate, Some(50.0));
+
+        assert_eq!(summary.errors_by_category[&ErrorCategory::Transport], 1);
+        assert_eq!(summary.errors_by_category[&ErrorCategory::Preflight], 2);
+        assert_eq!(summary.errors_by_category[&ErrorCategory::Instruction], 1);
+
+        // Only the instruction error is not expected from the migration; the
+        // transport error happened before the activation.
+        assert_eq!(summary.unexpected_errors, 1);
+    }
+
+    #[test]
+    fn summary_without_activation() {
+        let summary = RunSummary::new(&migration_records(), &MigrationTimeline::default());
+
+        assert_eq!(summary.outage.slots, 0);
+        assert_eq!(summary.outage.first_failed_slot, None);
+        assert_eq!(summary.post_upgrade_success_rate, None);
+        assert_eq!(summary.unexpected_errors, 0);
+    }
+
+    #[test]
+    fn percentiles_of_no_latencies() {
+        assert!(Percentiles::new(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn percentiles_of_a_single_latency() {
+        let percentiles = Percentiles::new(millis([5])).unwrap();
+
+        assert_eq!(percentiles.count, 1);
+        assert_eq!(percentiles.p50, Duration::from_millis(5));
+        assert_eq!(percentiles.p90, Duration::from_millis(5));
+        assert_eq!(percentiles.p99, Duration::from_millis(5));
+        assert_eq!(percentiles.max, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let percentiles = Percentiles::new(millis((1..=10).rev())).unwrap();
+
+        assert_eq!(percentiles.count, 10);
+        assert_eq!(percentiles.p50, Duration::from_millis(5));
+        assert_eq!(percentiles.p90, Duration::from_millis(9));
+        assert_eq!(percentiles.p99, Duration::from_millis(10));
+        assert_eq!(percentiles.max, Duration::from_millis(10));
+
+        let percentiles = Percentiles::new(millis(1..=100)).unwrap();
+
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p90, Duration::from_millis(90));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+        assert_eq!(percentiles.max, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn phase_at_the_boundaries() {
+        let timeline = timeline(Some(100), Some(150));
+
+        assert_eq!(timeline.phase(99), MigrationPhase::PreActivation);
+        assert_eq!(timeline.phase(100), MigrationPhase::ActivationEpoch);
+        assert_eq!(timeline.phase(149), MigrationPhase::ActivationEpoch);
+        assert_eq!(timeline.phase(150), MigrationPhase::MigrationSlot);
+        assert_eq!(timeline.phase(151), MigrationPhase::PostMigration);
+    }
+
+    #[test]
+    fn phase_without_migration() {
+        let timeline = timeline(Some(100), None);
+
+        assert_eq!(timeline.phase(99), MigrationPhase::PreActivation);
+        assert_eq!(timeline.phase(100), MigrationPhase::ActivationEpoch);
+        assert_eq!(timeline.phase(1_000), MigrationPhase::ActivationEpoch);
+    }
+
+    #[test]
+    fn phase_without_activation() {
+        let timeline = MigrationTimeline::default();
+
+        assert_eq!(timeline.phase(0), MigrationPhase::PreActivation);
+        assert_eq!(timeline.phase(1_000), MigrationPhase::PreActivation);
+
+        let timeline = MigrationTimeline::upgraded();
+
+        assert_eq!(timeline.phase(0), MigrationPhase::MigrationSlot);
+        assert_eq!(timeline.phase(1), MigrationPhase::PostMigration);
+    }
+}
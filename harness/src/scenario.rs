@@ -0,0 +1,659 @@
+//! Declarative migration scenarios.
+//!
+//! A scenario describes the migration targets to stage on the test validator
+//! and a timeline of steps to execute on top of it. Scenarios are loaded from
+//! TOML or JSON files, e.g.:
+//!
+//! ```toml
+//! [[steps]]
+//! action = "start-workload"
+//! name = "transfers"
+//! clients = 25
+//! mix = { transfer = 10, approve = 1 }
+//!
+//! [[steps]]
+//! action = "activate"
+//! target = "p-token"
+//!
+//! [[steps]]
+//! action = "wait-epoch"
+//!
+//! [[steps]]
+//! action = "assert-owner"
+//! program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+//! owner = "upgradeable"
+//! ```
+//!
+//! When no `[[targets]]` are specified, the p-token migration target is
+//! staged under the name `"p-token"`. Other targets are specified as:
+//!
+//! ```toml
+//! [[targets]]
+//! name = "my-program"
+//! feature_id = "<feature id>"
+//! buffer_address = "<address of the buffer with the new program>"
+//! program_id = "<address of the program to replace>"
+//! elf_name = "my_program"
+//! ```
+//!
+//! The `program_id` can be omitted for a target with the p-token feature id,
+//! which replaces SPL Token.
+
+use {
+    crate::{
+        client::{ClientRegistry, Workload},
+        invariant::Invariants,
+        report::{MigrationTimeline, Recorder},
+        validator::{FeatureState, MigrationTarget, ValidatorContext, P_TOKEN_MIGRATION},
+        verify::verify_migration,
+        workload::InstructionMix,
+    },
+    serde::{de::Error as _, Deserialize, Deserializer},
+    solana_rpc_client_api::config::CommitmentConfig,
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        fs,
+        io::{Error, ErrorKind, Result},
+        path::Path,
+        str::FromStr,
+        time::Duration,
+    },
+    tokio_util::sync::CancellationToken,
+};
+
+/// Name of the built-in p-token migration target.
+pub const P_TOKEN_TARGET: &str = P_TOKEN_MIGRATION.name;
+
+/// Time given to clients to check their accounts on a `check-invariants`
+/// step.
+const INVARIANT_CHECK_DELAY: Duration = Duration::from_secs(2);
+
+/// A migration scenario.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Migration targets staged at genesis.
+    #[serde(default = "default_targets")]
+    pub targets: Vec<TargetConfig>,
+
+    /// Steps to execute, in order.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// Configuration of a Core BPF migration target.
+#[derive(Deserialize)]
+#[serde(try_from = "TargetFile")]
+pub struct TargetConfig {
+    /// Name used to refer to the target in steps.
+    pub name: String,
+
+    /// Feature that triggers the migration.
+    pub feature_id: Pubkey,
+
+    /// Buffer account with the new program.
+    pub buffer_address: Pubkey,
+
+    /// Program replaced by the migration.
+    pub program_id: Pubkey,
+
+    /// Name of the ELF file of the new program, without extension.
+    pub elf_name: String,
+}
+
+// Target as written in scenario files, where the program id can be omitted
+// for the p-token feature.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TargetFile {
+    name: String,
+
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    feature_id: Pubkey,
+
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    buffer_address: Pubkey,
+
+    #[serde(default, deserialize_with = "deserialize_optional_pubkey")]
+    program_id: Option<Pubkey>,
+
+    elf_name: String,
+}
+
+impl TryFrom<TargetFile> for TargetConfig {
+    type Error = String;
+
+    fn try_from(target: TargetFile) -> std::result::Result<Self, Self::Error> {
+        let program_id = match target.program_id {
+            Some(program_id) => program_id,
+            None if target.feature_id == P_TOKEN_MIGRATION.feature_id => {
+                P_TOKEN_MIGRATION.program_id
+            }
+            None => return Err(format!("missing program_id of target: {}", target.name)),
+        };
+
+        Ok(Self {
+            name: target.name,
+            feature_id: target.feature_id,
+            buffer_address: target.buffer_address,
+            program_id,
+            elf_name: target.elf_name,
+        })
+    }
+}
+
+/// Returns the p-token migration target.
+pub fn default_targets() -> Vec<TargetConfig> {
+    vec![TargetConfig {
+        name: P_TOKEN_TARGET.to_string(),
+        feature_id: P_TOKEN_MIGRATION.feature_id,
+        buffer_address: P_TOKEN_MIGRATION.buffer_address,
+        program_id: P_TOKEN_MIGRATION.program_id,
+        elf_name: P_TOKEN_MIGRATION.elf_name.to_string(),
+    }]
+}
+
+impl TargetConfig {
+    /// Migration target described by the configuration.
+    pub fn as_migration_target(&self) -> MigrationTarget<'_> {
+        MigrationTarget {
+            name: &self.name,
+            feature_id: self.feature_id,
+            buffer_address: self.buffer_address,
+            program_id: self.program_id,
+            elf_name: &self.elf_name,
+        }
+    }
+}
+
+/// A step of the scenario timeline.
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Step {
+    /// Start a named group of clients sending transactions; clients only
+    /// send transfers when no instruction `mix` is specified.
+    StartWorkload {
+        /// Name of the workload.
+        name: String,
+        /// Number of clients.
+        clients: u64,
+        /// Instructions sent by the clients.
+        #[serde(default)]
+        mix: InstructionMix,
+    },
+
+    /// Stop a running workload.
+    StopWorkload {
+        /// Name of the workload.
+        name: String,
+    },
+
+    /// Activate the feature of a migration target.
+    Activate {
+        /// Name of the target.
+        target: String,
+    },
+
+    /// Withdraw the pending feature of a migration target before the next
+    /// epoch boundary, giving it back to the activator program.
+    Revoke {
+        /// Name of the target.
+        target: String,
+    },
+
+    /// Assert the lifecycle state of the feature of a migration target.
+    AssertFeature {
+        /// Name of the target.
+        target: String,
+        /// Expected state of the feature.
+        state: ExpectedFeatureState,
+    },
+
+    /// Sleep for the specified number of seconds.
+    Sleep {
+        /// Number of seconds.
+        seconds: u64,
+    },
+
+    /// Wait for the specified number of slots.
+    WaitSlots {
+        /// Number of slots.
+        slots: u64,
+    },
+
+    /// Wait for the start of the next epoch.
+    WaitEpoch,
+
+    /// Assert the owner of a program account.
+    AssertOwner {
+        /// Program account.
+        #[serde(deserialize_with = "deserialize_pubkey")]
+        program: Pubkey,
+        /// Expected owner of the account.
+        owner: Owner,
+    },
+
+    /// Assert whether an account exists or not.
+    AssertAccount {
+        /// Address of the account.
+        #[serde(deserialize_with = "deserialize_pubkey")]
+        address: Pubkey,
+        /// Whether the account is expected to exist.
+        exists: bool,
+    },
+
+    /// Assert that the program of a migration target is owned by the
+    /// upgradeable loader.
+    AssertMigrated {
+        /// Name of the target.
+        target: String,
+    },
+
+    /// Verify the program accounts of a migrated target against its staged
+    /// buffer.
+    VerifyMigration {
+        /// Name of the target.
+        target: String,
+    },
+
+    /// Check the token accounts of running clients and assert that no
+    /// invariant was violated so far.
+    CheckInvariants,
+}
+
+/// Expected state of a feature account.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExpectedFeatureState {
+    /// The feature account does not exist.
+    Missing,
+    /// Owned by the activator program, waiting to be activated.
+    Staged,
+    /// Owned by the Feature Gate program, waiting for the next epoch.
+    Pending,
+    /// Activated by the runtime.
+    Active,
+}
+
+impl ExpectedFeatureState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Staged => "staged",
+            Self::Pending => "pending",
+            Self::Active => "active",
+        }
+    }
+
+    fn matches(&self, state: FeatureState) -> bool {
+        matches!(
+            (self, state),
+            (Self::Missing, FeatureState::Missing)
+                | (Self::Staged, FeatureState::Staged)
+                | (Self::Pending, FeatureState::Pending)
+                | (Self::Active, FeatureState::Active { .. })
+        )
+    }
+}
+
+/// Expected owner of a program account.
+///
+/// Accepts `"loader-v2"`, `"upgradeable"` or the address of the owner.
+pub struct Owner(Pubkey);
+
+impl<'de> Deserialize<'de> for Owner {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.as_str() {
+            "loader-v2" => Ok(Owner(solana_sdk_ids::bpf_loader::id())),
+            "upgradeable" => Ok(Owner(solana_sdk_ids::bpf_loader_upgradeable::id())),
+            address => Pubkey::from_str(address)
+                .map(Owner)
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Pubkey, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Pubkey::from_str(&value).map_err(D::Error::custom)
+}
+
+fn deserialize_optional_pubkey<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Pubkey>, D::Error> {
+    deserialize_pubkey(deserializer).map(Some)
+}
+
+impl Scenario {
+    /// Loads a scenario from a TOML or JSON file, based on its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        let scenario: Scenario = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?,
+            _ => toml::from_str(&content)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?,
+        };
+
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    /// Migration targets of the scenario, in order.
+    pub fn migration_targets(&self) -> Vec<MigrationTarget<'_>> {
+        self.targets
+            .iter()
+            .map(TargetConfig::as_migration_target)
+            .collect()
+    }
+
+    // Check that steps only refer to known targets.
+    fn validate(&self) -> Result<()> {
+        for step in &self.steps {
+            if let Step::Activate { target }
+            | Step::Revoke { target }
+            | Step::AssertFeature { target, .. }
+            | Step::AssertMigrated { target }
+            | Step::VerifyMigration { target } = step
+            {
+                self.target(target)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn target(&self, name: &str) -> Result<&TargetConfig> {
+        self.targets
+            .iter()
+            .find(|t| t.name == name)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("unknown target: {name}")))
+    }
+
+    /// Runs the scenario steps on the provided validator.
+    ///
+    /// Returns an error when an assertion does not hold. Running workloads
+    /// are stopped when the scenario ends or is interrupted. The clients of
+    /// each workload are registered in `clients`, transactions are recorded
+    /// in the `recorder`, invariant violations in `invariants` and the slots
+    /// of the activation and migration in the `timeline`.
+    pub async fn run(
+        &self,
+        context: &ValidatorContext,
+        clients: &ClientRegistry,
+        recorder: &Recorder,
+        invariants: &Invariants,
+        timeline: &mut MigrationTimeline,
+        interrupted: &CancellationToken,
+    ) -> Result<()> {
+        let mut workloads = HashMap::new();
+        let mut next_client_id = 1;
+        // Targets activated since the last epoch boundary.
+        let mut activated = Vec::new();
+
+        let mut result = Ok(());
+
+        for (index, step) in self.steps.iter().enumerate() {
+            if interrupted.is_cancelled() {
+                break;
+            }
+
+            println!("▶️  [{:02}] {}", index + 1, step.describe());
+
+            match step {
+                Step::StartWorkload { name, clients, mix } => {
+                    let ids = next_client_id..next_client_id + clients;
+                    next_client_id += clients;
+
+                    let workload = Workload::spawn(
+                        context,
+                        ids,
+                        mix,
+                        recorder.clone(),
+                        invariants.clone(),
+                        interrupted,
+                    );
+                    clients.register(workload.stats());
+
+                    if let Some(previous) = workloads.insert(name, workload) {
+                        previous.stop().await;
+                    }
+                }
+                Step::StopWorkload { name } => {
+                    if let Some(workload) = workloads.remove(name) {
+                        workload.stop().await;
+                    }
+                }
+                Step::Activate { target } => {
+                    match context
+                        .activate_feature(&self.target(target)?.feature_id)
+                        .await
+                    {
+                        Ok(slot) => {
+                            timeline.record_activation(target, slot);
+                            activated.push(target.as_str());
+                        }
+                        Err(error) => {
+                            result = Err(Error::other(format!(
+                                "activation of '{target}' failed: {error}"
+                            )));
+                        }
+                    }
+                }
+                Step::Revoke { target } => {
+                    match context
+                        .revoke_pending_feature(&self.target(target)?.feature_id)
+                        .await
+                    {
+                        Ok(_) => {
+                            timeline.record_revocation(target);
+                            activated.retain(|activated| *activated != target.as_str());
+                        }
+                        Err(error) => {
+                            result = Err(Error::other(format!(
+                                "revocation of '{target}' failed: {error}"
+                            )));
+                        }
+                    }
+                }
+                Step::AssertFeature { target, state } => {
+                    let found = context.feature_state(&self.target(target)?.feature_id);
+
+                    if !state.matches(found) {
+                        result = Err(Error::other(format!(
+                            "feature of '{target}': expected {}, got {found}",
+                            state.name()
+                        )));
+                    }
+                }
+                Step::Sleep { seconds } => {
+                    interrupted
+                        .run_until_cancelled(tokio::time::sleep(Duration::from_secs(*seconds)))
+                        .await;
+                }
+                Step::WaitSlots { slots } => {
+                    let slot = context.get_slot().await + slots;
+                    interrupted
+                        .run_until_cancelled(
+                            context.wait_until_slot(slot, CommitmentConfig::processed()),
+                        )
+                        .await;
+                }
+                Step::WaitEpoch => {
+                    if interrupted
+                        .run_until_cancelled(context.wait_for_next_epoch())
+                        .await
+                        .is_none()
+                    {
+                        break;
+                    }
+                    // Activated targets are migrated at the first epoch
+                    // boundary after their activation, in the slot where the
+                    // runtime activates their feature.
+                    for target in activated.drain(..) {
+                        let feature_id = self.target(target)?.feature_id;
+                        if let FeatureState::Active { slot } = context.feature_state(&feature_id) {
+                            timeline.record_migration(target, slot);
+                        }
+                    }
+                }
+                Step::AssertOwner { program, owner } => {
+                    let account = context.get_account(program).await;
+                    match account {
+                        Some(account) if account.owner == owner.0 => (),
+                        Some(account) => {
+                            result = Err(Error::other(format!(
+                                "incorrect owner of {program}: expected {}, got {}",
+                                owner.0, account.owner
+                            )));
+                        }
+                        None => {
+                            result = Err(Error::other(format!("program not found: {program}")));
+                        }
+                    }
+                }
+                Step::AssertAccount { address, exists } => {
+                    let found = context.get_account(address).await.is_some();
+                    if found != *exists {
+                        result = Err(Error::other(format!(
+                            "account {address}: expected exists = {exists}, got {found}"
+                        )));
+                    }
+                }
+                Step::AssertMigrated { target } => {
+                    let program = self.target(target)?.program_id;
+                    let owner = context
+                        .get_account(&program)
+                        .await
+                        .map(|account| account.owner);
+
+                    if owner != Some(solana_sdk_ids::bpf_loader_upgradeable::id()) {
+                        result = Err(Error::other(format!(
+                            "program of '{target}' not migrated: {program} owned by {}",
+                            owner.map_or("-".to_string(), |owner| owner.to_string())
+                        )));
+                    }
+                }
+                Step::VerifyMigration { target } => {
+                    let config = self.target(target)?;
+
+                    let verification = verify_migration(
+                        &context.test_validator.get_async_rpc_client(),
+                        &config.as_migration_target(),
+                        context.staged_buffer(&config.buffer_address),
+                        timeline.target_migration_slot(target),
+                    )
+                    .await;
+
+                    println!("{verification}");
+
+                    if !verification.passed() {
+                        result = Err(Error::other(format!(
+                            "program accounts of '{target}' failed verification"
+                        )));
+                    }
+                }
+                Step::CheckInvariants => {
+                    invariants.request_check();
+                    // Give clients time to finish their current transaction.
+                    interrupted
+                        .run_until_cancelled(tokio::time::sleep(INVARIANT_CHECK_DELAY))
+                        .await;
+
+                    let violations = invariants.violations().len();
+                    if violations > 0 {
+                        result = Err(Error::other(format!(
+                            "{violations} token state invariant violations"
+                        )));
+                    }
+                }
+            }
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        for (_, workload) in workloads {
+            workload.stop().await;
+        }
+
+        result
+    }
+}
+
+impl Step {
+    fn describe(&self) -> String {
+        match self {
+            Step::StartWorkload { name, clients, .. } => {
+                format!("start workload '{name}' with {clients} clients")
+            }
+            Step::StopWorkload { name } => format!("stop workload '{name}'"),
+            Step::Activate { target } => format!("activate feature of '{target}'"),
+            Step::Revoke { target } => format!("revoke pending feature of '{target}'"),
+            Step::AssertFeature { target, state } => {
+                format!("assert feature of '{target}' is {}", state.name())
+            }
+            Step::Sleep { seconds } => format!("sleep {seconds}s"),
+            Step::WaitSlots { slots } => format!("wait {slots} slots"),
+            Step::WaitEpoch => "wait for next epoch".to_string(),
+            Step::AssertOwner { program, owner } => {
+                format!("assert owner of {program} is {}", owner.0)
+            }
+            Step::AssertAccount { address, exists } => {
+                format!("assert account {address} exists = {exists}")
+            }
+            Step::AssertMigrated { target } => format!("assert '{target}' is migrated"),
+            Step::VerifyMigration { target } => format!("verify program accounts of '{target}'"),
+            Step::CheckInvariants => "check token state invariants".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(feature_id: &Pubkey, program_id: Option<&Pubkey>) -> Result<Scenario> {
+        let program_id = program_id
+            .map(|program_id| format!("program_id = \"{program_id}\"\n"))
+            .unwrap_or_default();
+
+        toml::from_str(&format!(
+            "[[targets]]\n\
+             name = \"target\"\n\
+             feature_id = \"{feature_id}\"\n\
+             buffer_address = \"{}\"\n\
+             {program_id}\
+             elf_name = \"program\"\n",
+            Pubkey::new_unique()
+        ))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))
+    }
+
+    #[test]
+    fn target_with_program_id() {
+        let program_id = Pubkey::new_unique();
+        let scenario = targets(&Pubkey::new_unique(), Some(&program_id)).unwrap();
+
+        assert_eq!(scenario.targets[0].program_id, program_id);
+    }
+
+    #[test]
+    fn p_token_target_without_program_id() {
+        let scenario = targets(&P_TOKEN_MIGRATION.feature_id, None).unwrap();
+
+        assert_eq!(scenario.targets[0].program_id, P_TOKEN_MIGRATION.program_id);
+    }
+
+    #[test]
+    fn target_without_program_id() {
+        assert!(targets(&Pubkey::new_unique(), None).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Snapshots of the validator state before the feature activation.
+//!
+//! A snapshot directory contains a full snapshot archive taken by the test
+//! validator after the client accounts were created, together with the
+//! genesis and keypair files needed to start a validator from it, and a
+//! manifest with the keypairs of the client fixtures. Runs started from the
+//! same snapshot begin from an identical state.
+
+use {
+    crate::workload::{Fixture, MultisigOwner},
+    agave_snapshots::ArchiveFormat,
+    serde::{Deserialize, Serialize},
+    solana_sdk::{pubkey::Pubkey, signature::Keypair},
+    std::{
+        fs,
+        io::{Error, ErrorKind, Result},
+        path::{Path, PathBuf},
+        str::FromStr,
+        time::{Duration, Instant},
+    },
+};
+
+/// Name of the snapshot manifest file.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Name of the directory, inside the snapshot directory, of the ledger
+/// restored from the snapshot.
+const LEDGER_DIRECTORY: &str = "ledger";
+
+/// Files of the ledger needed to start a validator, other than the archive.
+const GENESIS_FILES: [&str; 2] = ["genesis.bin", "genesis.tar.bz2"];
+
+/// Maximum time to wait for the validator to write a snapshot archive.
+const ARCHIVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Description of a snapshot directory.
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Slot of the snapshot.
+    pub slot: u64,
+    /// File name of the full snapshot archive.
+    pub archive: String,
+    /// Number of slots per epoch of the ledger.
+    pub slots_per_epoch: u64,
+    /// Fixtures of the clients, already created in the snapshot.
+    pub fixtures: Vec<StoredFixture>,
+}
+
+/// Keypairs of a fixture, as bytes in the same format of keypair files.
+#[derive(Serialize, Deserialize)]
+pub struct StoredFixture {
+    authority: Vec<u8>,
+    multisig: Option<(Vec<u8>, Vec<Vec<u8>>)>,
+    mint: Vec<u8>,
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    freezable: Vec<u8>,
+    native: Vec<u8>,
+    delegate: String,
+}
+
+impl From<&Fixture> for StoredFixture {
+    fn from(fixture: &Fixture) -> Self {
+        let bytes = |keypair: &Keypair| keypair.to_bytes().to_vec();
+
+        Self {
+            authority: bytes(&fixture.authority),
+            multisig: fixture.multisig.as_ref().map(|multisig| {
+                (
+                    bytes(&multisig.account),
+                    multisig.signers.iter().map(bytes).collect(),
+                )
+            }),
+            mint: bytes(&fixture.mint),
+            source: bytes(&fixture.source),
+            destination: bytes(&fixture.destination),
+            freezable: bytes(&fixture.freezable),
+            native: bytes(&fixture.native),
+            delegate: fixture.delegate.to_string(),
+        }
+    }
+}
+
+impl TryFrom<&StoredFixture> for Fixture {
+    type Error = Error;
+
+    fn try_from(stored: &StoredFixture) -> Result<Self> {
+        let keypair = |bytes: &Vec<u8>| {
+            Keypair::try_from(bytes.as_slice())
+                .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()))
+        };
+
+        Ok(Self {
+            authority: keypair(&stored.authority)?,
+            multisig: stored
+                .multisig
+                .as_ref()
+                .map(|(account, signers)| {
+                    Ok::<_, Error>(MultisigOwner {
+                        account: keypair(account)?,
+                        signers: signers.iter().map(keypair).collect::<Result<_>>()?,
+                    })
+                })
+                .transpose()?,
+            mint: keypair(&stored.mint)?,
+            source: keypair(&stored.source)?,
+            destination: keypair(&stored.destination)?,
+            freezable: keypair(&stored.freezable)?,
+            native: keypair(&stored.native)?,
+            delegate: Pubkey::from_str(&stored.delegate)
+                .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()))?,
+        })
+    }
+}
+
+/// Waits for the validator to write a full snapshot archive of a slot
+/// greater than or equal to `min_slot` and copies it to `output`, together
+/// with the files needed to start a validator from it.
+pub async fn create(
+    ledger_path: &Path,
+    output: &Path,
+    min_slot: u64,
+    slots_per_epoch: u64,
+    fixtures: &[Fixture],
+) -> Result<SnapshotManifest> {
+    let started = Instant::now();
+
+    let (slot, archive) = loop {
+        if let Some(archive) = latest_full_snapshot_archive(ledger_path)? {
+            if archive.0 >= min_slot {
+                break archive;
+            }
+        }
+
+        if started.elapsed() > ARCHIVE_TIMEOUT {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "validator did not write a snapshot archive",
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    };
+
+    fs::create_dir_all(output)?;
+
+    for file in ledger_files(ledger_path, &archive)? {
+        fs::copy(ledger_path.join(&file), output.join(&file))?;
+    }
+
+    let manifest = SnapshotManifest {
+        slot,
+        archive,
+        slots_per_epoch,
+        fixtures: fixtures.iter().map(StoredFixture::from).collect(),
+    };
+
+    fs::write(
+        output.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    Ok(manifest)
+}
+
+/// Restores the snapshot into a new ledger, replacing any ledger restored
+/// previously from the same snapshot.
+///
+/// Returns the path of the ledger and the snapshot manifest.
+pub fn restore(snapshot: &Path) -> Result<(PathBuf, SnapshotManifest)> {
+    let manifest: SnapshotManifest =
+        serde_json::from_slice(&fs::read(snapshot.join(MANIFEST_FILE))?)?;
+
+    let ledger_path = snapshot.join(LEDGER_DIRECTORY);
+
+    if ledger_path.exists() {
+        fs::remove_dir_all(&ledger_path)?;
+    }
+    fs::create_dir_all(&ledger_path)?;
+
+    for file in ledger_files(snapshot, &manifest.archive)? {
+        fs::copy(snapshot.join(&file), ledger_path.join(&file))?;
+    }
+
+    Ok((ledger_path, manifest))
+}
+
+// Returns the names of the genesis, keypair and archive files in the
+// directory.
+fn ledger_files(directory: &Path, archive: &str) -> Result<Vec<String>> {
+    let mut files = vec![archive.to_string()];
+
+    for entry in fs::read_dir(directory)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if GENESIS_FILES.contains(&name.as_str()) || name.ends_with("-keypair.json") {
+            files.push(name);
+        }
+    }
+
+    Ok(files)
+}
+
+// Returns the slot and file name of the latest full snapshot archive in the
+// directory.
+fn latest_full_snapshot_archive(directory: &Path) -> Result<Option<(u64, String)>> {
+    let mut latest = None;
+
+    for entry in fs::read_dir(directory)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+
+        if let Some(slot) = full_snapshot_slot(&name) {
+            if latest.as_ref().is_none_or(|(latest, _)| slot > *latest) {
+                latest = Some((slot, name));
+            }
+        }
+    }
+
+    Ok(latest)
+}
+
+// Parses the slot of a full snapshot archive file name, in the form
+// `snapshot-<slot>-<hash>.<extension>`.
+fn full_snapshot_slot(file_name: &str) -> Option<u64> {
+    let (slot, rest) = file_name.strip_prefix("snapshot-")?.split_once('-')?;
+    let (_, extension) = rest.split_once('.')?;
+
+    ArchiveFormat::from_str(extension).ok()?;
+
+    slot.parse().ok()
+}
@@ -0,0 +1,757 @@
+//! Test validator with context for testing.
+
+#![allow(deprecated)]
+
+use {
+    crate::{
+        file::{copy_directory, load_account_dumps, FileReader},
+        snapshot::{self, SnapshotManifest},
+        workload::Fixture,
+    },
+    agave_feature_set::replace_spl_token_with_p_token::{
+        ID, PTOKEN_PROGRAM_BUFFER, SPL_TOKEN_PROGRAM_ID,
+    },
+    futures_util::StreamExt,
+    solana_feature_gate_interface::{from_account, Feature},
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_pubsub_client::nonblocking::pubsub_client::PubsubClient,
+    solana_rpc::rpc::JsonRpcConfig,
+    solana_rpc_client_api::config::CommitmentConfig,
+    solana_runtime::bank::Bank,
+    solana_sdk::{
+        account::{Account, AccountSharedData, ReadableAccount, WritableAccount},
+        epoch_schedule::EpochSchedule,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::{read_keypair_file, write_keypair_file, Keypair, Signature},
+        signer::Signer,
+        transaction::Transaction,
+    },
+    solana_test_validator::{TestValidator, TestValidatorGenesis, UpgradeableProgramInfo},
+    std::{
+        collections::HashMap,
+        fmt, fs, io,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// Default path of the validator ledger.
+pub const LEDGER_PATH: &str = "./target/migration-ledger";
+
+/// Program migration staged on the test validator.
+#[derive(Clone, Copy)]
+pub struct MigrationTarget<'a> {
+    /// Name of the target, used to report its migration.
+    pub name: &'a str,
+    /// Feature that triggers the migration.
+    pub feature_id: Pubkey,
+    /// Buffer account with the new program.
+    pub buffer_address: Pubkey,
+    /// Program replaced by the migration.
+    pub program_id: Pubkey,
+    /// Name of the ELF file of the new program, without extension.
+    pub elf_name: &'a str,
+}
+
+/// Migration of SPL Token to p-token.
+pub const P_TOKEN_MIGRATION: MigrationTarget<'static> = MigrationTarget {
+    name: "p-token",
+    feature_id: ID,
+    buffer_address: PTOKEN_PROGRAM_BUFFER,
+    program_id: SPL_TOKEN_PROGRAM_ID,
+    elf_name: "p_token",
+};
+
+/// Lifecycle state of a feature account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureState {
+    /// The feature account does not exist.
+    Missing,
+    /// Owned by the activator program, waiting to be activated.
+    Staged,
+    /// Owned by the Feature Gate program; the runtime activates it at the
+    /// next epoch boundary.
+    Pending,
+    /// Activated by the runtime at the slot.
+    Active {
+        /// Activation slot.
+        slot: u64,
+    },
+}
+
+impl FeatureState {
+    /// Names of the lifecycle states.
+    pub const NAMES: [&'static str; 4] = ["missing", "staged", "pending", "active"];
+
+    /// Name of the lifecycle state.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Missing => Self::NAMES[0],
+            Self::Staged => Self::NAMES[1],
+            Self::Pending => Self::NAMES[2],
+            Self::Active { .. } => Self::NAMES[3],
+        }
+    }
+}
+
+impl fmt::Display for FeatureState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Active { slot } => write!(f, "active (slot {slot})"),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// Feature account of a feature, together with its status in the runtime.
+#[derive(Clone, Copy, Debug)]
+pub struct FeatureInfo {
+    /// Feature of the account.
+    pub feature_id: Pubkey,
+    /// Owner of the feature account, if it exists.
+    pub owner: Option<Pubkey>,
+    /// Activation slot stored in the feature account.
+    pub activated_at: Option<u64>,
+    /// Whether the feature set of the bank considers the feature active.
+    pub runtime_active: bool,
+}
+
+impl FeatureInfo {
+    /// Reads the feature from the accounts and feature set of a bank.
+    pub fn read(bank: &Bank, feature_id: &Pubkey) -> Self {
+        let account = bank.get_account(feature_id);
+
+        Self {
+            feature_id: *feature_id,
+            owner: account.as_ref().map(|account| *account.owner()),
+            activated_at: account
+                .as_ref()
+                .and_then(from_account)
+                .and_then(|feature| feature.activated_at),
+            runtime_active: bank.feature_set.is_active(feature_id),
+        }
+    }
+
+    /// Lifecycle state of the feature, from the owner and activation slot of its account.
+    pub fn state(&self) -> FeatureState {
+        match self.owner {
+            None => FeatureState::Missing,
+            Some(owner) if owner == solana_sdk_ids::feature::id() => match self.activated_at {
+                Some(slot) => FeatureState::Active { slot },
+                None => FeatureState::Pending,
+            },
+            Some(_) => FeatureState::Staged,
+        }
+    }
+
+    /// Name of the owner of the feature account.
+    pub fn owner_label(&self) -> String {
+        match self.owner {
+            Some(owner) if owner == solana_sdk_ids::feature::id() => "feature gate".to_string(),
+            Some(owner) if owner == cbmt_program_activator::id() => "activator".to_string(),
+            Some(owner) => owner.to_string(),
+            None => "-".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FeatureInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} | owner {} | activated at ",
+            self.state(),
+            self.owner_label()
+        )?;
+        match self.activated_at {
+            Some(slot) => write!(f, "slot {slot}")?,
+            None => f.write_str("-")?,
+        }
+        let runtime = if self.runtime_active {
+            "active"
+        } else {
+            "inactive"
+        };
+        write!(f, " | runtime {runtime}")
+    }
+}
+
+/// Buffer account staged with the new program of a migration target, as
+/// read from the ledger before the migration.
+pub struct StagedBuffer {
+    /// ELF of the new program.
+    pub elf: Vec<u8>,
+    /// Authority of the buffer, which the migration keeps as the upgrade
+    /// authority of the program.
+    pub authority: Option<Pubkey>,
+}
+
+/// Reads features from an existing ledger, returning the slot of its last
+/// root and the feature of each id.
+///
+/// The validator is started on a temporary copy of the ledger, so the ledger
+/// is left untouched and no ELF is needed. Fails when the ledger does not
+/// exist.
+pub async fn read_ledger_features(
+    ledger_path: &Path,
+    feature_ids: &[Pubkey],
+) -> io::Result<(u64, Vec<FeatureInfo>)> {
+    if !ledger_path.join("genesis.bin").exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("ledger not found: {}", ledger_path.display()),
+        ));
+    }
+
+    solana_logger::setup_with_default("off");
+
+    let copy = std::env::temp_dir().join(format!("migration-ledger-{}", std::process::id()));
+    if copy.exists() {
+        fs::remove_dir_all(&copy)?;
+    }
+    copy_directory(ledger_path, &copy)?;
+
+    let (test_validator, _) = TestValidatorGenesis::default()
+        .ledger_path(&copy)
+        .start_async()
+        .await;
+
+    // The root bank holds the state of the ledger, before any new slot.
+    let bank = test_validator.bank_forks().read().unwrap().root_bank();
+    let features = feature_ids
+        .iter()
+        .map(|feature_id| FeatureInfo::read(&bank, feature_id))
+        .collect();
+    let slot = bank.slot();
+
+    drop(bank);
+    drop(test_validator);
+    fs::remove_dir_all(&copy)?;
+
+    Ok((slot, features))
+}
+
+/// Ledger from which a validator was started.
+pub enum LedgerOrigin {
+    /// New ledger, with the migration targets staged at genesis.
+    New,
+    /// Existing ledger, reused as it is.
+    Existing,
+    /// Ledger restored from a snapshot, described by its manifest.
+    Snapshot(SnapshotManifest),
+}
+
+/// Test validator with the migration targets staged, together with the
+/// payer of its transactions.
+pub struct ValidatorContext {
+    /// Running test validator.
+    pub test_validator: TestValidator,
+    /// Payer of the transactions, funded at genesis.
+    pub payer: Keypair,
+    /// Epoch schedule of the ledger; it might differ from the requested one
+    /// when an existing ledger is reused.
+    pub epoch_schedule: EpochSchedule,
+    /// Client of the validator PubSub service, used to wait for slots.
+    pubsub_client: PubsubClient,
+    /// Fixtures already created on the validator, e.g. restored from a
+    /// snapshot, available to the clients.
+    fixtures: Mutex<Vec<Fixture>>,
+    /// Staged buffers, by buffer address.
+    staged_buffers: HashMap<Pubkey, StagedBuffer>,
+}
+
+impl ValidatorContext {
+    /// Fetches an account, returning `None` if it does not exist.
+    pub async fn get_account(&self, account_id: &Pubkey) -> Option<Account> {
+        self.test_validator
+            .get_async_rpc_client()
+            .get_account(account_id)
+            .await
+            .ok()
+    }
+
+    /// Makes fixtures already created on the validator, e.g. restored from a
+    /// snapshot, available to the clients.
+    pub fn add_fixtures(&self, fixtures: impl IntoIterator<Item = Fixture>) {
+        self.fixtures.lock().unwrap().extend(fixtures);
+    }
+
+    /// Takes one of the fixtures already created on the validator, if any.
+    pub fn take_fixture(&self) -> Option<Fixture> {
+        self.fixtures.lock().unwrap().pop()
+    }
+
+    /// Returns the buffer staged for a migration target; it is unavailable
+    /// when the validator started after the migration consumed it, e.g. on a
+    /// reused ledger.
+    pub fn staged_buffer(&self, buffer_address: &Pubkey) -> Option<&StagedBuffer> {
+        self.staged_buffers.get(buffer_address)
+    }
+
+    /// Asserts that the program account is owned by `owner`.
+    ///
+    /// Panics when the account does not exist or has a different owner.
+    pub async fn assert_owner(&self, program_id: &Pubkey, owner: &Pubkey) {
+        let account = self.get_account(program_id).await.unwrap();
+        assert!(
+            account.owner == *owner,
+            "incorrect program owner: expected {:?}, got {:?}",
+            owner,
+            account.owner
+        );
+    }
+
+    /// Sends a transaction with the instructions and waits for its
+    /// confirmation, returning its signature.
+    ///
+    /// Fails when the transaction cannot be sent or its execution fails.
+    pub async fn send_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[&Keypair],
+    ) -> io::Result<Signature> {
+        let rpc_client = self.test_validator.get_async_rpc_client();
+        let (latest_blockhash, _) = rpc_client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+            .map_err(io::Error::other)?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(payer),
+            signers,
+            latest_blockhash,
+        );
+        rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Slot processed by the validator.
+    pub async fn get_slot(&self) -> u64 {
+        self.test_validator
+            .get_async_rpc_client()
+            .get_slot()
+            .await
+            .unwrap()
+    }
+
+    async fn get_slot_with_commitment(&self, commitment: CommitmentConfig) -> u64 {
+        self.test_validator
+            .get_async_rpc_client()
+            .get_slot_with_commitment(commitment)
+            .await
+            .unwrap()
+    }
+
+    /// Activates the feature, returning the slot in which the activation
+    /// transaction was processed.
+    pub async fn activate_feature(&self, feature_id: &Pubkey) -> io::Result<u64> {
+        self.activate_features(&[*feature_id]).await
+    }
+
+    /// Activates the features in a single transaction, returning the slot in
+    /// which it was processed.
+    ///
+    /// Fails when the activation transaction fails, e.g. when a feature is
+    /// not staged, or its status cannot be read.
+    pub async fn activate_features(&self, feature_ids: &[Pubkey]) -> io::Result<u64> {
+        self.send_feature_instruction(cbmt_program_activator::activate_features(feature_ids))
+            .await
+    }
+
+    /// Withdraws the pending feature before the runtime activates it, giving
+    /// the feature account back to the activator program; returns the slot
+    /// in which the transaction was processed.
+    pub async fn revoke_pending_feature(&self, feature_id: &Pubkey) -> io::Result<u64> {
+        self.revoke_pending_features(&[*feature_id]).await
+    }
+
+    /// Withdraws the pending features in a single transaction, returning the
+    /// slot in which it was processed.
+    ///
+    /// The transaction is processed by the copy of the activator deployed at
+    /// the Feature Gate program id on ledgers created by the harness. It
+    /// fails when a feature is not pending, e.g. once the epoch boundary
+    /// activated it.
+    pub async fn revoke_pending_features(&self, feature_ids: &[Pubkey]) -> io::Result<u64> {
+        self.send_feature_instruction(cbmt_program_activator::revoke_pending_features(feature_ids))
+            .await
+    }
+
+    // Sends an activator instruction, returning the slot in which its
+    // transaction was processed.
+    async fn send_feature_instruction(&self, instruction: Instruction) -> io::Result<u64> {
+        let signature = self
+            .send_transaction(&[instruction], &self.payer.pubkey(), &[&self.payer])
+            .await?;
+
+        self.test_validator
+            .get_async_rpc_client()
+            .get_signature_statuses(&[signature])
+            .await
+            .map_err(io::Error::other)?
+            .value
+            .first()
+            .cloned()
+            .flatten()
+            .map(|status| status.slot)
+            .ok_or_else(|| io::Error::other(format!("status of {signature} not found")))
+    }
+
+    /// Reads the feature account and the feature set of the working bank.
+    pub fn feature_info(&self, feature_id: &Pubkey) -> FeatureInfo {
+        let bank = self
+            .test_validator
+            .bank_forks()
+            .read()
+            .unwrap()
+            .working_bank();
+        FeatureInfo::read(&bank, feature_id)
+    }
+
+    /// Lifecycle state of the feature on the working bank.
+    pub fn feature_state(&self, feature_id: &Pubkey) -> FeatureState {
+        self.feature_info(feature_id).state()
+    }
+
+    /// Waits until `slot` is reached at the given commitment level, returning
+    /// the slot observed.
+    ///
+    /// Processed slots are tracked with a slot subscription and finalized
+    /// slots, i.e. roots of the single validator, with a root subscription;
+    /// the confirmed slot is queried on every new processed slot.
+    pub async fn wait_until_slot(&self, slot: u64, commitment: CommitmentConfig) -> u64 {
+        if commitment.is_finalized() {
+            let (mut roots, unsubscribe) = self.pubsub_client.root_subscribe().await.unwrap();
+            let mut current = self.get_slot_with_commitment(commitment).await;
+
+            while current < slot {
+                current = roots.next().await.expect("root subscription closed");
+            }
+
+            unsubscribe().await;
+            return current;
+        }
+
+        let (mut slots, unsubscribe) = self.pubsub_client.slot_subscribe().await.unwrap();
+        let mut current = self.get_slot_with_commitment(commitment).await;
+
+        while current < slot {
+            let info = slots.next().await.expect("slot subscription closed");
+
+            current = if commitment.is_confirmed() {
+                self.get_slot_with_commitment(commitment).await
+            } else {
+                info.slot
+            };
+        }
+
+        unsubscribe().await;
+        current
+    }
+
+    /// Waits until the next slot is processed.
+    pub async fn wait_for_next_slot(&self) {
+        let slot = self
+            .get_slot_with_commitment(CommitmentConfig::processed())
+            .await;
+        self.wait_until_slot(slot + 1, CommitmentConfig::processed())
+            .await;
+    }
+
+    /// Waits until the program is owned by `owner` and can be invoked, i.e.
+    /// one slot after its owner changed.
+    pub async fn wait_for_program(&self, program_id: &Pubkey, owner: &Pubkey) {
+        while self
+            .get_account(program_id)
+            .await
+            .is_none_or(|account| account.owner != *owner)
+        {
+            self.wait_for_next_slot().await;
+        }
+        self.wait_for_next_slot().await;
+    }
+
+    /// Waits until the first slot of `epoch` is reached at the given
+    /// commitment level, returning the slot.
+    pub async fn wait_until_epoch(&self, epoch: u64, commitment: CommitmentConfig) -> u64 {
+        let first_slot = self.epoch_schedule.get_first_slot_in_epoch(epoch);
+        self.wait_until_slot(first_slot, commitment).await;
+        first_slot
+    }
+
+    /// Waits for the start of the next epoch, returning its first slot.
+    ///
+    /// The slot is confirmed, so accounts read afterwards reflect the epoch
+    /// boundary, e.g. the migration of activated features.
+    pub async fn wait_for_next_epoch(&self) -> u64 {
+        let slot = self
+            .get_slot_with_commitment(CommitmentConfig::processed())
+            .await;
+        let epoch = self.epoch_schedule.get_epoch(slot);
+
+        self.wait_until_epoch(epoch + 1, CommitmentConfig::confirmed())
+            .await
+    }
+
+    /// Waits until the feature is active, returning its activation slot.
+    pub async fn wait_until_feature_active(&self, feature_id: &Pubkey) -> u64 {
+        let (mut slots, unsubscribe) = self.pubsub_client.slot_subscribe().await.unwrap();
+
+        let slot = loop {
+            if let FeatureState::Active { slot } = self.feature_state(feature_id) {
+                break slot;
+            }
+            slots.next().await.expect("slot subscription closed");
+        };
+
+        unsubscribe().await;
+        slot
+    }
+
+    /// Starts the validator, reusing the ledger if it exists.
+    ///
+    /// Accounts of `account_dumps` are added to the genesis of new ledgers,
+    /// together with the migration target accounts; they are ignored when
+    /// the ledger is reused. Fails when an account dump cannot be loaded.
+    pub async fn start(
+        migration_targets: &[MigrationTarget<'_>],
+        elf_directory: &str,
+        ledger_path: &Path,
+        slots_per_epoch: u64,
+        account_dumps: &[PathBuf],
+    ) -> io::Result<Self> {
+        solana_logger::setup_with_default("off");
+
+        let existing = ledger_path.exists();
+
+        // Accounts are part of the genesis, so they can only be added to new
+        // ledgers.
+        let dumped_accounts = if existing {
+            Vec::new()
+        } else {
+            load_account_dumps(account_dumps)?
+        };
+
+        let file_reader = FileReader::new(&[elf_directory]);
+
+        let epoch_schedule = EpochSchedule::custom(slots_per_epoch, slots_per_epoch, false);
+
+        let deactivate_list = migration_targets
+            .iter()
+            .map(|mt| mt.feature_id)
+            .collect::<Vec<_>>();
+
+        // ELFs are only required to stage the buffers of new ledgers; account
+        // dumps of the buffers replace them.
+        let buffers = if existing {
+            Vec::new()
+        } else {
+            migration_targets
+                .iter()
+                .map(|mt| {
+                    let elf = file_reader.load_program_elf(mt.elf_name);
+                    (mt.buffer_address, buffer_account(&elf))
+                })
+                .collect()
+        };
+
+        let accounts = migration_targets
+            .iter()
+            .map(|mt| (mt.feature_id, staged_feature_account()))
+            .chain(buffers)
+            .chain(dumped_accounts);
+
+        // The activator is also deployed at the Feature Gate program id,
+        // which owns pending features, so that they can be revoked.
+        let activator = |program_id| UpgradeableProgramInfo {
+            program_id,
+            loader: solana_sdk_ids::bpf_loader_upgradeable::id(),
+            program_path: elf_path(elf_directory, "cbmt_program_activator"),
+            upgrade_authority: Pubkey::new_unique(),
+        };
+        let bpf_programs = &[
+            activator(cbmt_program_activator::id()),
+            activator(solana_sdk_ids::feature::id()),
+        ];
+
+        let (test_validator, payer) = TestValidatorGenesis::default()
+            .ledger_path(ledger_path)
+            .epoch_schedule(epoch_schedule)
+            .deactivate_features(&deactivate_list)
+            .add_accounts(accounts)
+            .add_upgradeable_programs_with_path(bpf_programs)
+            .rpc_config(JsonRpcConfig {
+                enable_rpc_transaction_history: true,
+                ..JsonRpcConfig::default_for_test()
+            })
+            .start_async()
+            .await;
+
+        let payer = if ledger_path.join("payer-keypair.json").exists() {
+            read_keypair_file(ledger_path.join("payer-keypair.json")).unwrap()
+        } else {
+            write_keypair_file(
+                &payer,
+                ledger_path.join("payer-keypair.json").to_str().unwrap(),
+            )
+            .unwrap();
+            payer
+        };
+
+        let epoch_schedule = test_validator
+            .get_async_rpc_client()
+            .get_epoch_schedule()
+            .await
+            .unwrap();
+        let pubsub_client = PubsubClient::new(&test_validator.rpc_pubsub_url())
+            .await
+            .unwrap();
+
+        // The staged buffers are read back from the ledger, so they hold the
+        // actual ELF and authority, e.g. of dumped buffer accounts.
+        let rpc_client = test_validator.get_async_rpc_client();
+        let mut staged_buffers = HashMap::new();
+
+        for mt in migration_targets {
+            let buffer = rpc_client
+                .get_account(&mt.buffer_address)
+                .await
+                .ok()
+                .and_then(|account| read_staged_buffer(&account));
+
+            if let Some(buffer) = buffer {
+                staged_buffers.insert(mt.buffer_address, buffer);
+            }
+        }
+
+        Ok(Self {
+            test_validator,
+            payer,
+            epoch_schedule,
+            pubsub_client,
+            fixtures: Mutex::default(),
+            staged_buffers,
+        })
+    }
+
+    /// Starts the validator from a snapshot created by [`snapshot::create`],
+    /// returning the manifest of the snapshot.
+    ///
+    /// The ledger is restored inside the snapshot directory and the fixtures
+    /// of the snapshot are made available to the clients. Fails when the
+    /// snapshot cannot be restored or one of its fixtures is invalid.
+    pub async fn start_from_snapshot(
+        migration_targets: &[MigrationTarget<'_>],
+        elf_directory: &str,
+        snapshot: &Path,
+    ) -> io::Result<(Self, SnapshotManifest)> {
+        let (ledger_path, manifest) = snapshot::restore(snapshot)?;
+
+        let context = Self::start(
+            migration_targets,
+            elf_directory,
+            &ledger_path,
+            manifest.slots_per_epoch,
+            // Accounts are already part of the snapshot.
+            &[],
+        )
+        .await?;
+
+        context.add_fixtures(
+            manifest
+                .fixtures
+                .iter()
+                .map(Fixture::try_from)
+                .collect::<io::Result<Vec<_>>>()?,
+        );
+
+        Ok((context, manifest))
+    }
+
+    /// Starts the validator from the `snapshot`, if one is given, or with
+    /// [`ValidatorContext::start`] otherwise, returning the origin of its
+    /// ledger.
+    pub async fn start_or_restore(
+        migration_targets: &[MigrationTarget<'_>],
+        elf_directory: &str,
+        ledger_path: &Path,
+        slots_per_epoch: u64,
+        account_dumps: &[PathBuf],
+        snapshot: Option<&Path>,
+    ) -> io::Result<(Self, LedgerOrigin)> {
+        if let Some(snapshot) = snapshot {
+            let (context, manifest) =
+                Self::start_from_snapshot(migration_targets, elf_directory, snapshot).await?;
+            return Ok((context, LedgerOrigin::Snapshot(manifest)));
+        }
+
+        let origin = if ledger_path.exists() {
+            LedgerOrigin::Existing
+        } else {
+            LedgerOrigin::New
+        };
+
+        let context = Self::start(
+            migration_targets,
+            elf_directory,
+            ledger_path,
+            slots_per_epoch,
+            account_dumps,
+        )
+        .await?;
+
+        Ok((context, origin))
+    }
+}
+
+// Create a "staged" feature account, owned by the activator program.
+fn staged_feature_account() -> AccountSharedData {
+    let space = Feature::size_of();
+    let lamports = Rent::default().minimum_balance(space);
+    AccountSharedData::new(lamports, space, &cbmt_program_activator::id())
+}
+
+// Create a buffer account with the provided ELF and no authority.
+fn buffer_account(elf: &[u8]) -> AccountSharedData {
+    let space = UpgradeableLoaderState::size_of_buffer(elf.len());
+    let lamports = Rent::default().minimum_balance(space);
+    let mut account = AccountSharedData::new_data_with_space(
+        lamports,
+        &UpgradeableLoaderState::Buffer {
+            authority_address: None,
+        },
+        space,
+        &solana_sdk_ids::bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    account.data_as_mut_slice()[UpgradeableLoaderState::size_of_buffer_metadata()..]
+        .copy_from_slice(elf);
+    account
+}
+
+// Read the ELF and authority of a buffer account, if it is one.
+fn read_staged_buffer(account: &Account) -> Option<StagedBuffer> {
+    if account.owner != solana_sdk_ids::bpf_loader_upgradeable::id() {
+        return None;
+    }
+
+    match account.deserialize_data().ok()? {
+        UpgradeableLoaderState::Buffer { authority_address } => Some(StagedBuffer {
+            elf: account
+                .data
+                .get(UpgradeableLoaderState::size_of_buffer_metadata()..)?
+                .to_vec(),
+            authority: authority_address,
+        }),
+        _ => None,
+    }
+}
+
+fn elf_path(elf_dir: &str, program_name: &str) -> PathBuf {
+    PathBuf::from(elf_dir).join(format!("{}.so", program_name))
+}
@@ -0,0 +1,316 @@
+//! Verification of the program accounts after a Core BPF migration.
+//!
+//! The migration replaces the target program with a Loader v3 program,
+//! whose program data account holds the ELF of the staged buffer. The buffer
+//! account is consumed and the new accounts are funded with the rent-exempt
+//! minimum balance for their size.
+
+use {
+    crate::{
+        report::MigrationTimeline,
+        validator::{MigrationTarget, StagedBuffer, ValidatorContext},
+    },
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{account::Account, pubkey::Pubkey},
+    solana_sdk_ids::bpf_loader_upgradeable,
+    std::fmt,
+};
+
+/// Outcome of a verification check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The value found is the expected one.
+    Passed,
+    /// The value found differs from the expected one.
+    Failed,
+    /// The expected value is unknown, so the check was not run.
+    Skipped,
+}
+
+impl From<bool> for CheckStatus {
+    fn from(passed: bool) -> Self {
+        if passed {
+            Self::Passed
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// Result of a single verification check.
+pub struct Check {
+    /// Name of the check.
+    pub name: &'static str,
+    /// Outcome of the check.
+    pub status: CheckStatus,
+    /// Values found, and expected, by the check.
+    pub details: String,
+}
+
+/// Verification of the program accounts of a migration target.
+pub struct Verification {
+    /// Program of the target.
+    pub program_id: Pubkey,
+    /// Checks of the program accounts.
+    pub checks: Vec<Check>,
+}
+
+impl Verification {
+    /// Indicates whether no check failed and at least one check was run.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed)
+            && self
+                .checks
+                .iter()
+                .any(|check| check.status == CheckStatus::Passed)
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Program: {}", self.program_id)?;
+
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Passed => "✅",
+                CheckStatus::Failed => "❌",
+                CheckStatus::Skipped => "➖",
+            };
+            write!(f, "\n  {mark} {}: {}", check.name, check.details)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Verifies the program accounts of a migrated target against its staged
+/// buffer: the program data holds its ELF and keeps its authority.
+///
+/// The ELF and upgrade authority are skipped when the `staged_buffer` is
+/// unavailable, and the deployment slot, which must be the migration slot,
+/// when the `migration_slot` is unknown.
+pub async fn verify_migration(
+    rpc_client: &RpcClient,
+    target: &MigrationTarget<'_>,
+    staged_buffer: Option<&StagedBuffer>,
+    migration_slot: Option<u64>,
+) -> Verification {
+    let mut checks = Vec::new();
+    let mut check = |name, status, details: String| {
+        checks.push(Check {
+            name,
+            status,
+            details,
+        })
+    };
+
+    let (programdata_address, _) =
+        Pubkey::find_program_address(&[target.program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    // Program account.
+
+    let program = get_account(rpc_client, &target.program_id).await;
+
+    match program.as_ref().map(loader_state) {
+        Some(Some(UpgradeableLoaderState::Program {
+            programdata_address: address,
+        })) => check(
+            "program account",
+            (address == programdata_address).into(),
+            format!("program data address {address}"),
+        ),
+        Some(_) => check(
+            "program account",
+            CheckStatus::Failed,
+            "not an upgradeable program".to_string(),
+        ),
+        None => check(
+            "program account",
+            CheckStatus::Failed,
+            "not found".to_string(),
+        ),
+    }
+
+    // Program data account.
+
+    let programdata = get_account(rpc_client, &programdata_address).await;
+
+    match programdata
+        .as_ref()
+        .map(|account| (account, loader_state(account)))
+    {
+        Some((
+            account,
+            Some(UpgradeableLoaderState::ProgramData {
+                slot,
+                upgrade_authority_address,
+            }),
+        )) => {
+            match staged_buffer {
+                Some(staged_buffer) => check(
+                    "upgrade authority",
+                    (upgrade_authority_address == staged_buffer.authority).into(),
+                    format!(
+                        "{upgrade_authority_address:?} (staged: {:?})",
+                        staged_buffer.authority
+                    ),
+                ),
+                None => check(
+                    "upgrade authority",
+                    CheckStatus::Skipped,
+                    format!("{upgrade_authority_address:?} (staged buffer unavailable)"),
+                ),
+            }
+
+            match migration_slot {
+                Some(migration_slot) => check(
+                    "deployment slot",
+                    (slot == migration_slot).into(),
+                    format!("{slot} (migration slot: {migration_slot})"),
+                ),
+                None => check(
+                    "deployment slot",
+                    CheckStatus::Skipped,
+                    format!("{slot} (migration slot unknown)"),
+                ),
+            }
+
+            let elf = account
+                .data
+                .get(UpgradeableLoaderState::size_of_programdata_metadata()..)
+                .unwrap_or_default();
+            let found = solana_sha256_hasher::hash(elf);
+
+            match staged_buffer {
+                Some(staged_buffer) => {
+                    let staged_elf = staged_buffer.elf.as_slice();
+                    let expected = solana_sha256_hasher::hash(staged_elf);
+
+                    check(
+                        "ELF",
+                        (elf == staged_elf).into(),
+                        format!(
+                            "{} bytes, sha256 {found} (staged: {} bytes, sha256 {expected})",
+                            elf.len(),
+                            staged_elf.len()
+                        ),
+                    );
+                }
+                None => check(
+                    "ELF",
+                    CheckStatus::Skipped,
+                    format!(
+                        "{} bytes, sha256 {found} (staged ELF unavailable)",
+                        elf.len()
+                    ),
+                ),
+            }
+        }
+        Some(_) => check(
+            "program data account",
+            CheckStatus::Failed,
+            format!("{programdata_address} is not a program data account"),
+        ),
+        None => check(
+            "program data account",
+            CheckStatus::Failed,
+            format!("{programdata_address} not found"),
+        ),
+    }
+
+    // Buffer account.
+
+    let buffer = get_account(rpc_client, &target.buffer_address).await;
+
+    check(
+        "buffer consumed",
+        buffer.is_none().into(),
+        match buffer {
+            Some(account) => format!(
+                "{} still holds {} lamports",
+                target.buffer_address, account.lamports
+            ),
+            None => format!("{} closed", target.buffer_address),
+        },
+    );
+
+    // Lamports of the new accounts.
+
+    for (name, account) in [
+        ("program rent", &program),
+        ("program data rent", &programdata),
+    ] {
+        if let Some(account) = account {
+            let minimum = rpc_client
+                .get_minimum_balance_for_rent_exemption(account.data.len())
+                .await
+                .unwrap_or_default();
+
+            check(
+                name,
+                (account.lamports == minimum).into(),
+                format!(
+                    "{} lamports (rent-exempt minimum: {minimum})",
+                    account.lamports
+                ),
+            );
+        }
+    }
+
+    Verification {
+        program_id: target.program_id,
+        checks,
+    }
+}
+
+/// Verifies the program accounts of the targets upgraded on the validator,
+/// against their staged buffers and the migration slots of the `timeline`.
+///
+/// Returns the verification of each target, in order, or `None` for the
+/// targets whose program is not upgraded.
+pub async fn verify_targets(
+    context: &ValidatorContext,
+    targets: &[MigrationTarget<'_>],
+    timeline: &MigrationTimeline,
+) -> Vec<Option<Verification>> {
+    let rpc_client = context.test_validator.get_async_rpc_client();
+    let mut verifications = Vec::with_capacity(targets.len());
+
+    for target in targets {
+        let upgraded = get_account(&rpc_client, &target.program_id)
+            .await
+            .is_some_and(|account| account.owner == bpf_loader_upgradeable::id());
+
+        if !upgraded {
+            verifications.push(None);
+            continue;
+        }
+
+        let verification = verify_migration(
+            &rpc_client,
+            target,
+            context.staged_buffer(&target.buffer_address),
+            timeline.target_migration_slot(target.name),
+        )
+        .await;
+
+        verifications.push(Some(verification));
+    }
+
+    verifications
+}
+
+async fn get_account(rpc_client: &RpcClient, address: &Pubkey) -> Option<Account> {
+    rpc_client.get_account(address).await.ok()
+}
+
+// Returns the state of a Loader v3 account.
+fn loader_state(account: &Account) -> Option<UpgradeableLoaderState> {
+    if account.owner != bpf_loader_upgradeable::id() {
+        return None;
+    }
+    account.deserialize_data().ok()
+}
@@ -0,0 +1,843 @@
+//! Token instruction workloads.
+//!
+//! Clients send transactions with a token instruction picked from a weighted
+//! [`InstructionMix`]. Each transaction is built from the accounts of a
+//! client [`Fixture`] and includes any instructions needed for the target
+//! instruction to succeed, e.g. creating the account that is closed.
+
+use {
+    crate::client::send_transaction,
+    agave_feature_set::replace_spl_token_with_p_token::SPL_TOKEN_PROGRAM_ID,
+    rand::{
+        distributions::{Distribution, WeightedIndex},
+        Rng,
+    },
+    serde::Deserialize,
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+        signature::Keypair, signer::Signer,
+    },
+    solana_system_interface::instruction::{create_account, transfer as system_transfer},
+    spl_token_interface::{
+        instruction::{
+            amount_to_ui_amount, approve, approve_checked, burn, burn_checked, close_account,
+            freeze_account, get_account_data_size, initialize_account, initialize_account2,
+            initialize_account3, initialize_immutable_owner, initialize_mint, initialize_mint2,
+            initialize_multisig, initialize_multisig2, mint_to, mint_to_checked, revoke,
+            set_authority, sync_native, thaw_account, transfer, transfer_checked,
+            ui_amount_to_amount, AuthorityType,
+        },
+        native_mint,
+        state::{Account, AccountState, Mint, Multisig},
+    },
+    std::{collections::BTreeMap, fmt, str::FromStr},
+};
+
+/// Decimals of the fixture mint.
+const DECIMALS: u8 = 0;
+
+/// Initial token balance of the source account.
+pub const INITIAL_BALANCE: u64 = 1_000_000_000;
+
+/// Initial balance of the wrapped SOL account, in lamports on top of the
+/// rent-exempt minimum.
+pub const INITIAL_NATIVE_BALANCE: u64 = 1_000_000;
+
+/// Number of signers of multisig accounts.
+const MULTISIG_SIGNERS: usize = 3;
+
+/// Number of signatures required by multisig accounts.
+const MULTISIG_THRESHOLD: usize = 2;
+
+/// Token instruction sent by the clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionKind {
+    /// Transfers tokens between accounts.
+    Transfer,
+    /// Transfers tokens, checking the decimals of the mint.
+    TransferChecked,
+    /// Approves a delegate.
+    Approve,
+    /// Approves a delegate, checking the decimals of the mint.
+    ApproveChecked,
+    /// Revokes the delegate.
+    Revoke,
+    /// Mints tokens, checking the decimals of the mint.
+    MintToChecked,
+    /// Burns tokens.
+    Burn,
+    /// Burns tokens, checking the decimals of the mint.
+    BurnChecked,
+    /// Closes a token account.
+    CloseAccount,
+    /// Freezes a token account.
+    FreezeAccount,
+    /// Thaws a frozen token account.
+    ThawAccount,
+    /// Changes an authority of a mint or token account.
+    SetAuthority,
+    /// Syncs the amount of a wrapped SOL account with its lamports.
+    SyncNative,
+    /// Initializes a token account, with the owner in the instruction data.
+    InitializeAccount2,
+    /// Initializes a token account, without the rent sysvar.
+    InitializeAccount3,
+    /// Initializes a mint.
+    InitializeMint,
+    /// Initializes a token account.
+    InitializeAccount,
+    /// Initializes a multisig account.
+    InitializeMultisig,
+    /// Mints tokens.
+    MintTo,
+    /// Initializes a multisig account, without the rent sysvar.
+    InitializeMultisig2,
+    /// Initializes a mint, without the rent sysvar.
+    InitializeMint2,
+    /// Returns the size of a token account.
+    GetAccountDataSize,
+    /// Marks an uninitialized account as having an immutable owner.
+    InitializeImmutableOwner,
+    /// Converts an amount to its UI representation.
+    AmountToUiAmount,
+    /// Converts a UI amount to an amount.
+    UiAmountToAmount,
+}
+
+impl InstructionKind {
+    /// All instructions.
+    pub const ALL: [InstructionKind; 25] = [
+        InstructionKind::Transfer,
+        InstructionKind::TransferChecked,
+        InstructionKind::Approve,
+        InstructionKind::ApproveChecked,
+        InstructionKind::Revoke,
+        InstructionKind::MintToChecked,
+        InstructionKind::Burn,
+        InstructionKind::BurnChecked,
+        InstructionKind::CloseAccount,
+        InstructionKind::FreezeAccount,
+        InstructionKind::ThawAccount,
+        InstructionKind::SetAuthority,
+        InstructionKind::SyncNative,
+        InstructionKind::InitializeAccount2,
+        InstructionKind::InitializeAccount3,
+        InstructionKind::InitializeMint,
+        InstructionKind::InitializeAccount,
+        InstructionKind::InitializeMultisig,
+        InstructionKind::MintTo,
+        InstructionKind::InitializeMultisig2,
+        InstructionKind::InitializeMint2,
+        InstructionKind::GetAccountDataSize,
+        InstructionKind::InitializeImmutableOwner,
+        InstructionKind::AmountToUiAmount,
+        InstructionKind::UiAmountToAmount,
+    ];
+
+    /// Name of the instruction, e.g. in reports and instruction mixes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstructionKind::Transfer => "transfer",
+            InstructionKind::TransferChecked => "transfer_checked",
+            InstructionKind::Approve => "approve",
+            InstructionKind::ApproveChecked => "approve_checked",
+            InstructionKind::Revoke => "revoke",
+            InstructionKind::MintToChecked => "mint_to_checked",
+            InstructionKind::Burn => "burn",
+            InstructionKind::BurnChecked => "burn_checked",
+            InstructionKind::CloseAccount => "close_account",
+            InstructionKind::FreezeAccount => "freeze_account",
+            InstructionKind::ThawAccount => "thaw_account",
+            InstructionKind::SetAuthority => "set_authority",
+            InstructionKind::SyncNative => "sync_native",
+            InstructionKind::InitializeAccount2 => "initialize_account2",
+            InstructionKind::InitializeAccount3 => "initialize_account3",
+            InstructionKind::InitializeMint => "initialize_mint",
+            InstructionKind::InitializeAccount => "initialize_account",
+            InstructionKind::InitializeMultisig => "initialize_multisig",
+            InstructionKind::MintTo => "mint_to",
+            InstructionKind::InitializeMultisig2 => "initialize_multisig2",
+            InstructionKind::InitializeMint2 => "initialize_mint2",
+            InstructionKind::GetAccountDataSize => "get_account_data_size",
+            InstructionKind::InitializeImmutableOwner => "initialize_immutable_owner",
+            InstructionKind::AmountToUiAmount => "amount_to_ui_amount",
+            InstructionKind::UiAmountToAmount => "ui_amount_to_amount",
+        }
+    }
+
+    /// Indicates whether the instruction creates an account that cannot be
+    /// closed, i.e. a mint or a multisig. These instructions are only
+    /// measured by the `bench` and `diff` commands, since clients sending
+    /// them would leak the lamports of the new accounts.
+    pub fn is_bench_only(&self) -> bool {
+        matches!(
+            self,
+            InstructionKind::InitializeMint
+                | InstructionKind::InitializeMint2
+                | InstructionKind::InitializeMultisig
+                | InstructionKind::InitializeMultisig2
+        )
+    }
+
+    /// Indicates whether the instruction is signed by an authority, which
+    /// can be a multisig account.
+    pub fn has_authority(&self) -> bool {
+        matches!(
+            self,
+            InstructionKind::Transfer
+                | InstructionKind::Approve
+                | InstructionKind::Revoke
+                | InstructionKind::SetAuthority
+                | InstructionKind::MintTo
+                | InstructionKind::Burn
+                | InstructionKind::CloseAccount
+                | InstructionKind::FreezeAccount
+                | InstructionKind::ThawAccount
+                | InstructionKind::TransferChecked
+                | InstructionKind::ApproveChecked
+                | InstructionKind::MintToChecked
+                | InstructionKind::BurnChecked
+        )
+    }
+}
+
+impl fmt::Display for InstructionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for InstructionKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        InstructionKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("unknown instruction: {name}"))
+    }
+}
+
+/// Weighted mix of token instructions.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "BTreeMap<InstructionKind, u32>")]
+pub struct InstructionMix {
+    kinds: Vec<InstructionKind>,
+    distribution: WeightedIndex<u32>,
+}
+
+impl InstructionMix {
+    /// Instructions with a non-zero weight.
+    pub fn kinds(&self) -> &[InstructionKind] {
+        &self.kinds
+    }
+
+    /// Picks an instruction according to the weights.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> InstructionKind {
+        self.kinds[self.distribution.sample(rng)]
+    }
+}
+
+impl Default for InstructionMix {
+    fn default() -> Self {
+        BTreeMap::from([(InstructionKind::Transfer, 1)])
+            .try_into()
+            .unwrap()
+    }
+}
+
+impl TryFrom<BTreeMap<InstructionKind, u32>> for InstructionMix {
+    type Error = String;
+
+    fn try_from(weights: BTreeMap<InstructionKind, u32>) -> Result<Self, Self::Error> {
+        let (kinds, weights): (Vec<_>, Vec<_>) = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .unzip();
+
+        if let Some(kind) = kinds.iter().find(|kind| kind.is_bench_only()) {
+            return Err(format!(
+                "{kind} cannot be part of an instruction mix: it creates accounts that are never \
+                 closed"
+            ));
+        }
+
+        let distribution = WeightedIndex::new(weights)
+            .map_err(|_| "instruction mix needs at least one non-zero weight".to_string())?;
+
+        Ok(Self {
+            kinds,
+            distribution,
+        })
+    }
+}
+
+/// Parses a mix in the format `<instruction>=<weight>,...`, e.g.
+/// `transfer=10,approve=1`. A weight of `1` is used when omitted.
+impl FromStr for InstructionMix {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut weights = BTreeMap::new();
+
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, weight) = entry.split_once('=').unwrap_or((entry, "1"));
+            let weight = weight
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid weight for {name}: {weight}"))?;
+
+            weights.insert(name.trim().parse::<InstructionKind>()?, weight);
+        }
+
+        weights.try_into()
+    }
+}
+
+/// Accounts used by a client to send token instructions.
+///
+/// The owner of the fixture is the mint and freeze authority, as well as the
+/// owner of all token accounts. It is either the `authority` or, for multisig
+/// fixtures, the multisig account.
+pub struct Fixture {
+    /// Owner of the fixture, unless it is owned by a multisig account.
+    pub authority: Keypair,
+    /// Multisig account owning the fixture, if any.
+    pub multisig: Option<MultisigOwner>,
+    /// Mint of the token accounts.
+    pub mint: Keypair,
+    /// Account holding the minted tokens.
+    pub source: Keypair,
+    /// Account receiving transfers.
+    pub destination: Keypair,
+    /// Account that is frozen and thawed.
+    pub freezable: Keypair,
+    /// Wrapped SOL account.
+    pub native: Keypair,
+    /// Delegate of approvals.
+    pub delegate: Pubkey,
+}
+
+/// Multisig account with its signers.
+pub struct MultisigOwner {
+    /// Multisig account.
+    pub account: Keypair,
+    /// Signers of the multisig account.
+    pub signers: Vec<Keypair>,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self {
+            authority: Keypair::new(),
+            multisig: None,
+            mint: Keypair::new(),
+            source: Keypair::new(),
+            destination: Keypair::new(),
+            freezable: Keypair::new(),
+            native: Keypair::new(),
+            delegate: Pubkey::new_unique(),
+        }
+    }
+}
+
+impl Fixture {
+    /// Fixture owned by a multisig account.
+    pub fn multisig() -> Self {
+        Self {
+            multisig: Some(MultisigOwner {
+                account: Keypair::new(),
+                signers: (0..MULTISIG_SIGNERS).map(|_| Keypair::new()).collect(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Address of the fixture owner.
+    pub fn owner(&self) -> Pubkey {
+        match &self.multisig {
+            Some(multisig) => multisig.account.pubkey(),
+            None => self.authority.pubkey(),
+        }
+    }
+
+    /// Keypairs that sign on behalf of the owner.
+    pub fn owner_signers(&self) -> Vec<&Keypair> {
+        match &self.multisig {
+            Some(multisig) => multisig.signers.iter().take(MULTISIG_THRESHOLD).collect(),
+            None => vec![&self.authority],
+        }
+    }
+
+    // Multisig signers passed to token instructions; empty when the owner is
+    // not a multisig.
+    fn signer_ids(&self) -> Vec<Pubkey> {
+        match &self.multisig {
+            Some(_) => self.owner_signers().iter().map(|s| s.pubkey()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Creates and initializes the fixture accounts.
+    pub async fn create(&self, rpc_client: &RpcClient, payer: &Keypair) {
+        let payer_id = payer.pubkey();
+        let owner = self.owner();
+        let mint = self.mint.pubkey();
+        let signer_ids = self.signer_ids();
+        let signer_ids = signer_ids.iter().collect::<Vec<_>>();
+
+        if let Some(multisig) = &self.multisig {
+            let signers = multisig
+                .signers
+                .iter()
+                .map(|s| s.pubkey())
+                .collect::<Vec<_>>();
+            let instructions = [
+                create_token_account(&payer_id, &owner, Multisig::LEN, 0),
+                initialize_multisig(
+                    &SPL_TOKEN_PROGRAM_ID,
+                    &owner,
+                    &signers.iter().collect::<Vec<_>>(),
+                    MULTISIG_THRESHOLD as u8,
+                )
+                .unwrap(),
+            ];
+
+            send_transaction(
+                rpc_client,
+                &instructions,
+                &payer_id,
+                &[payer, &multisig.account],
+            )
+            .await
+            .1
+            .unwrap();
+        }
+
+        let instructions = [
+            create_token_account(&payer_id, &mint, Mint::LEN, 0),
+            create_token_account(&payer_id, &self.source.pubkey(), Account::LEN, 0),
+            create_token_account(&payer_id, &self.destination.pubkey(), Account::LEN, 0),
+            initialize_mint(&SPL_TOKEN_PROGRAM_ID, &mint, &owner, Some(&owner), DECIMALS).unwrap(),
+            initialize_account(&SPL_TOKEN_PROGRAM_ID, &self.source.pubkey(), &mint, &owner)
+                .unwrap(),
+            initialize_account(
+                &SPL_TOKEN_PROGRAM_ID,
+                &self.destination.pubkey(),
+                &mint,
+                &owner,
+            )
+            .unwrap(),
+            mint_to(
+                &SPL_TOKEN_PROGRAM_ID,
+                &mint,
+                &self.source.pubkey(),
+                &owner,
+                &signer_ids,
+                INITIAL_BALANCE,
+            )
+            .unwrap(),
+        ];
+
+        let mut signers = vec![payer, &self.mint, &self.source, &self.destination];
+        signers.extend(self.owner_signers());
+
+        send_transaction(rpc_client, &instructions, &payer_id, &signers)
+            .await
+            .1
+            .unwrap();
+
+        let instructions = [
+            create_token_account(&payer_id, &self.freezable.pubkey(), Account::LEN, 0),
+            create_token_account(
+                &payer_id,
+                &self.native.pubkey(),
+                Account::LEN,
+                INITIAL_NATIVE_BALANCE,
+            ),
+            initialize_account3(
+                &SPL_TOKEN_PROGRAM_ID,
+                &self.freezable.pubkey(),
+                &mint,
+                &owner,
+            )
+            .unwrap(),
+            initialize_account3(
+                &SPL_TOKEN_PROGRAM_ID,
+                &self.native.pubkey(),
+                &native_mint::id(),
+                &owner,
+            )
+            .unwrap(),
+        ];
+
+        send_transaction(
+            rpc_client,
+            &instructions,
+            &payer_id,
+            &[payer, &self.freezable, &self.native],
+        )
+        .await
+        .1
+        .unwrap();
+    }
+}
+
+/// State of the fixture that affects which instructions are valid.
+#[derive(Default)]
+pub struct FixtureState {
+    /// Whether the freezable account is frozen.
+    pub frozen: bool,
+}
+
+impl FixtureState {
+    /// Updates the state after a transaction with the given instruction
+    /// succeeded.
+    pub fn apply(&mut self, kind: InstructionKind) {
+        match kind {
+            InstructionKind::FreezeAccount => self.frozen = true,
+            InstructionKind::ThawAccount => self.frozen = false,
+            _ => (),
+        }
+    }
+
+    /// Whether a transaction with the given instruction changes the state.
+    pub fn is_changed_by(kind: InstructionKind) -> bool {
+        matches!(
+            kind,
+            InstructionKind::FreezeAccount | InstructionKind::ThawAccount
+        )
+    }
+
+    /// Reloads the state from the fixture accounts.
+    pub async fn refresh(&mut self, rpc_client: &RpcClient, fixture: &Fixture) {
+        if let Ok(account) = rpc_client.get_account(&fixture.freezable.pubkey()).await {
+            if let Ok(account) = Account::unpack(&account.data) {
+                self.frozen = account.state == AccountState::Frozen;
+            }
+        }
+    }
+}
+
+/// Transaction instructions to execute a token instruction.
+pub struct Operation {
+    /// Token instruction executed.
+    pub kind: InstructionKind,
+    /// Instructions of the transaction.
+    pub instructions: Vec<Instruction>,
+    /// Index of the instruction of `kind` in `instructions`.
+    pub target: usize,
+    /// Accounts created by the transaction, which must sign it.
+    pub new_accounts: Vec<Keypair>,
+    /// Whether the fixture owner must sign the transaction.
+    pub needs_owner: bool,
+}
+
+impl Operation {
+    /// Builds the transaction instructions of `kind` for the fixture in its current `state`.
+    pub fn new(
+        kind: InstructionKind,
+        fixture: &Fixture,
+        state: &FixtureState,
+        payer: &Pubkey,
+    ) -> Self {
+        let token = &SPL_TOKEN_PROGRAM_ID;
+        let owner = fixture.owner();
+        let signer_ids = fixture.signer_ids();
+        let signers = signer_ids.iter().collect::<Vec<_>>();
+        let signers = signers.as_slice();
+        let mint = fixture.mint.pubkey();
+        let source = fixture.source.pubkey();
+        let freezable = fixture.freezable.pubkey();
+
+        let single = |instruction: Instruction, needs_owner: bool| Self {
+            kind,
+            instructions: vec![instruction],
+            target: 0,
+            new_accounts: Vec::new(),
+            needs_owner,
+        };
+
+        // Creates a new token account, initializes it and closes it.
+        let new_account = |account: Keypair, initialize: Vec<Instruction>, target: usize| {
+            let mut instructions = vec![create_token_account(
+                payer,
+                &account.pubkey(),
+                Account::LEN,
+                0,
+            )];
+            instructions.extend(initialize);
+            instructions
+                .push(close_account(token, &account.pubkey(), payer, &owner, signers).unwrap());
+
+            Self {
+                kind,
+                instructions,
+                target,
+                new_accounts: vec![account],
+                needs_owner: true,
+            }
+        };
+
+        // Creates and initializes a new account that cannot be closed, i.e.
+        // a mint or a multisig.
+        let new_unclosable = |account: Keypair, space: usize, initialize: Instruction| Self {
+            kind,
+            instructions: vec![
+                create_token_account(payer, &account.pubkey(), space, 0),
+                initialize,
+            ],
+            target: 1,
+            new_accounts: vec![account],
+            needs_owner: false,
+        };
+
+        // Signers of new multisig accounts.
+        let multisig_signers = (0..MULTISIG_SIGNERS)
+            .map(|_| Pubkey::new_unique())
+            .collect::<Vec<_>>();
+        let multisig_signers = multisig_signers.iter().collect::<Vec<_>>();
+
+        let account = Keypair::new();
+        let address = account.pubkey();
+
+        match kind {
+            InstructionKind::InitializeMint => {
+                let initialize =
+                    initialize_mint(token, &address, &owner, Some(&owner), DECIMALS).unwrap();
+                new_unclosable(account, Mint::LEN, initialize)
+            }
+            InstructionKind::InitializeMint2 => {
+                let initialize =
+                    initialize_mint2(token, &address, &owner, Some(&owner), DECIMALS).unwrap();
+                new_unclosable(account, Mint::LEN, initialize)
+            }
+            InstructionKind::InitializeMultisig => {
+                let initialize = initialize_multisig(
+                    token,
+                    &address,
+                    &multisig_signers,
+                    MULTISIG_THRESHOLD as u8,
+                )
+                .unwrap();
+                new_unclosable(account, Multisig::LEN, initialize)
+            }
+            InstructionKind::InitializeMultisig2 => {
+                let initialize = initialize_multisig2(
+                    token,
+                    &address,
+                    &multisig_signers,
+                    MULTISIG_THRESHOLD as u8,
+                )
+                .unwrap();
+                new_unclosable(account, Multisig::LEN, initialize)
+            }
+            InstructionKind::InitializeAccount | InstructionKind::CloseAccount => {
+                let initialize = initialize_account(token, &address, &mint, &owner).unwrap();
+                let target = if kind == InstructionKind::CloseAccount {
+                    2
+                } else {
+                    1
+                };
+                new_account(account, vec![initialize], target)
+            }
+            InstructionKind::InitializeAccount2 => {
+                let initialize = initialize_account2(token, &address, &mint, &owner).unwrap();
+                new_account(account, vec![initialize], 1)
+            }
+            InstructionKind::InitializeAccount3 => {
+                let initialize = initialize_account3(token, &address, &mint, &owner).unwrap();
+                new_account(account, vec![initialize], 1)
+            }
+            InstructionKind::InitializeImmutableOwner => {
+                let initialize = vec![
+                    initialize_immutable_owner(token, &address).unwrap(),
+                    initialize_account3(token, &address, &mint, &owner).unwrap(),
+                ];
+                new_account(account, initialize, 1)
+            }
+            InstructionKind::Transfer => single(
+                transfer(
+                    token,
+                    &source,
+                    &fixture.destination.pubkey(),
+                    &owner,
+                    signers,
+                    1,
+                )
+                .unwrap(),
+                true,
+            ),
+            InstructionKind::TransferChecked => single(
+                transfer_checked(
+                    token,
+                    &source,
+                    &mint,
+                    &fixture.destination.pubkey(),
+                    &owner,
+                    signers,
+                    1,
+                    DECIMALS,
+                )
+                .unwrap(),
+                true,
+            ),
+            InstructionKind::Approve => single(
+                approve(token, &source, &fixture.delegate, &owner, signers, 1).unwrap(),
+                true,
+            ),
+            InstructionKind::ApproveChecked => single(
+                approve_checked(
+                    token,
+                    &source,
+                    &mint,
+                    &fixture.delegate,
+                    &owner,
+                    signers,
+                    1,
+                    DECIMALS,
+                )
+                .unwrap(),
+                true,
+            ),
+            InstructionKind::Revoke => {
+                single(revoke(token, &source, &owner, signers).unwrap(), true)
+            }
+            InstructionKind::SetAuthority => single(
+                set_authority(
+                    token,
+                    &source,
+                    Some(&owner),
+                    AuthorityType::CloseAccount,
+                    &owner,
+                    signers,
+                )
+                .unwrap(),
+                true,
+            ),
+            InstructionKind::MintTo => single(
+                mint_to(token, &mint, &source, &owner, signers, 1).unwrap(),
+                true,
+            ),
+            InstructionKind::MintToChecked => single(
+                mint_to_checked(token, &mint, &source, &owner, signers, 1, DECIMALS).unwrap(),
+                true,
+            ),
+            InstructionKind::Burn => single(
+                burn(token, &source, &mint, &owner, signers, 1).unwrap(),
+                true,
+            ),
+            InstructionKind::BurnChecked => single(
+                burn_checked(token, &source, &mint, &owner, signers, 1, DECIMALS).unwrap(),
+                true,
+            ),
+            InstructionKind::FreezeAccount | InstructionKind::ThawAccount => {
+                let freeze = freeze_account(token, &freezable, &mint, &owner, signers).unwrap();
+                let thaw = thaw_account(token, &freezable, &mint, &owner, signers).unwrap();
+
+                // Prepend the opposite instruction when the account is not in
+                // the state expected by the target instruction.
+                let instructions = match (kind, state.frozen) {
+                    (InstructionKind::FreezeAccount, false) => vec![freeze],
+                    (InstructionKind::FreezeAccount, true) => vec![thaw, freeze],
+                    (_, true) => vec![thaw],
+                    (_, false) => vec![freeze, thaw],
+                };
+
+                Self {
+                    kind,
+                    target: instructions.len() - 1,
+                    instructions,
+                    new_accounts: Vec::new(),
+                    needs_owner: true,
+                }
+            }
+            InstructionKind::SyncNative => Self {
+                kind,
+                instructions: vec![
+                    system_transfer(payer, &fixture.native.pubkey(), 1),
+                    sync_native(token, &fixture.native.pubkey()).unwrap(),
+                ],
+                target: 1,
+                new_accounts: Vec::new(),
+                needs_owner: false,
+            },
+            InstructionKind::GetAccountDataSize => {
+                single(get_account_data_size(token, &mint).unwrap(), false)
+            }
+            InstructionKind::AmountToUiAmount => {
+                single(amount_to_ui_amount(token, &mint, 1).unwrap(), false)
+            }
+            InstructionKind::UiAmountToAmount => {
+                single(ui_amount_to_amount(token, &mint, "1").unwrap(), false)
+            }
+        }
+    }
+
+    /// Returns the signers of the transaction, other than the payer.
+    pub fn signers<'a>(&'a self, fixture: &'a Fixture) -> Vec<&'a Keypair> {
+        let mut signers = self.new_accounts.iter().collect::<Vec<_>>();
+        if self.needs_owner {
+            signers.extend(fixture.owner_signers());
+        }
+        signers
+    }
+}
+
+// Creates an account owned by the token program with an additional amount
+// of lamports on top of the rent-exempt minimum.
+fn create_token_account(
+    payer: &Pubkey,
+    account: &Pubkey,
+    space: usize,
+    lamports: u64,
+) -> Instruction {
+    create_account(
+        payer,
+        account,
+        Rent::default().minimum_balance(space) + lamports,
+        space as u64,
+        &SPL_TOKEN_PROGRAM_ID,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mix_with_weights() {
+        let mix = "transfer=10, approve=1,burn"
+            .parse::<InstructionMix>()
+            .unwrap();
+
+        assert_eq!(
+            mix.kinds(),
+            &[
+                InstructionKind::Transfer,
+                InstructionKind::Approve,
+                InstructionKind::Burn
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_mix_skips_zero_weights() {
+        let mix = "transfer=1,revoke=0".parse::<InstructionMix>().unwrap();
+
+        assert_eq!(mix.kinds(), &[InstructionKind::Transfer]);
+    }
+
+    #[test]
+    fn parse_invalid_mix() {
+        assert!("".parse::<InstructionMix>().is_err());
+        assert!("transfer=0".parse::<InstructionMix>().is_err());
+        assert!("transfer=x".parse::<InstructionMix>().is_err());
+        assert!("unknown=1".parse::<InstructionMix>().is_err());
+        assert!("transfer,initialize_mint"
+            .parse::<InstructionMix>()
+            .is_err());
+    }
+}
@@ -0,0 +1,43 @@
+//! Errors of the activator program.
+
+use {solana_program::program_error::ProgramError, std::fmt};
+
+/// Custom errors returned by the activator program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivatorError {
+    /// A feature account is not owned by the program processing the
+    /// instruction.
+    InvalidAccountOwner,
+    /// A feature account is not writable.
+    AccountNotWritable,
+    /// The current slot is before the activation slot.
+    SlotNotReached,
+    /// The current epoch is before the activation epoch.
+    EpochNotReached,
+    /// A revocation is not processed by the copy of the program deployed at
+    /// the Feature Gate program id.
+    NotFeatureGate,
+    /// A feature account to revoke is already activated.
+    FeatureActive,
+}
+
+impl From<ActivatorError> for ProgramError {
+    fn from(error: ActivatorError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+impl fmt::Display for ActivatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAccountOwner => f.write_str("feature account not owned by the program"),
+            Self::AccountNotWritable => f.write_str("feature account not writable"),
+            Self::SlotNotReached => f.write_str("activation slot not reached"),
+            Self::EpochNotReached => f.write_str("activation epoch not reached"),
+            Self::NotFeatureGate => {
+                f.write_str("revocation not processed by the Feature Gate program")
+            }
+            Self::FeatureActive => f.write_str("feature already activated"),
+        }
+    }
+}
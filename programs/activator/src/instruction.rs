@@ -0,0 +1,107 @@
+//! Instructions of the activator program and their builders.
+
+use {
+    crate::FEATURE_GATE_PROGRAM_ID,
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+};
+
+/// Instructions supported by the activator program.
+///
+/// Every instruction takes the feature accounts to activate or revoke, which
+/// must be writable and owned by the program processing the instruction:
+///
+///   0..N. `[writable]` Feature accounts.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ActivatorInstruction {
+    /// Activate the feature accounts.
+    ///
+    /// Empty instruction data is also interpreted as `Activate`.
+    Activate,
+
+    /// Activate the feature accounts when the current slot is at or after
+    /// `slot`.
+    ActivateAtSlot { slot: u64 },
+
+    /// Activate the feature accounts when the current epoch is at or after
+    /// `epoch`.
+    ActivateAtEpoch { epoch: u64 },
+
+    /// Withdraw the pending activation of the feature accounts, giving them
+    /// back to the activator program.
+    ///
+    /// Only processed by the copy of the program deployed at the Feature
+    /// Gate program id, which owns pending feature accounts; fails once a
+    /// feature is activated.
+    Revoke,
+}
+
+impl ActivatorInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.is_empty() {
+            return Ok(Self::Activate);
+        }
+        borsh::from_slice(input).map_err(|_| ProgramError::InvalidInstructionData)
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        borsh::to_vec(self).unwrap()
+    }
+}
+
+/// Creates an instruction to activate a single feature.
+pub fn activate_feature(feature_id: &Pubkey) -> Instruction {
+    activate_features(&[*feature_id])
+}
+
+/// Creates an instruction to activate many features at once.
+pub fn activate_features(feature_ids: &[Pubkey]) -> Instruction {
+    instruction(crate::id(), &ActivatorInstruction::Activate, feature_ids)
+}
+
+/// Creates an instruction to activate features at or after a slot.
+pub fn activate_features_at_slot(feature_ids: &[Pubkey], slot: u64) -> Instruction {
+    instruction(
+        crate::id(),
+        &ActivatorInstruction::ActivateAtSlot { slot },
+        feature_ids,
+    )
+}
+
+/// Creates an instruction to activate features at or after an epoch.
+pub fn activate_features_at_epoch(feature_ids: &[Pubkey], epoch: u64) -> Instruction {
+    instruction(
+        crate::id(),
+        &ActivatorInstruction::ActivateAtEpoch { epoch },
+        feature_ids,
+    )
+}
+
+/// Creates an instruction to withdraw the pending activation of features,
+/// sent to the copy of the program deployed at the Feature Gate program id.
+pub fn revoke_pending_features(feature_ids: &[Pubkey]) -> Instruction {
+    instruction(
+        FEATURE_GATE_PROGRAM_ID,
+        &ActivatorInstruction::Revoke,
+        feature_ids,
+    )
+}
+
+fn instruction(
+    program_id: Pubkey,
+    instruction: &ActivatorInstruction,
+    feature_ids: &[Pubkey],
+) -> Instruction {
+    Instruction::new_with_bytes(
+        program_id,
+        &instruction.pack(),
+        feature_ids
+            .iter()
+            .map(|feature_id| AccountMeta::new(*feature_id, false))
+            .collect(),
+    )
+}
@@ -5,13 +5,28 @@
 //!
 //! The test harness will create a feature account owned by this program at
 //! genesis. Then, it can invoke this program to assign ownership to
-//! `Feature1111...`, activating the feature without the keypair.
-use solana_program::{
-    account_info::AccountInfo,
-    entrypoint::ProgramResult,
-    instruction::{AccountMeta, Instruction},
-    program_error::ProgramError,
-    pubkey::Pubkey,
+//! `Feature1111...`, activating the feature without the keypair. Several
+//! features can be activated by the same instruction, optionally only at or
+//! after a given slot or epoch.
+//!
+//! A pending activation can be withdrawn before the next epoch boundary to
+//! rehearse a rollback. Pending feature accounts are owned by
+//! `Feature1111...`, so the harness also deploys this program at
+//! `Feature1111...`, where it stands in for the Feature Gate program: its
+//! `Revoke` instruction gives the accounts back to this program without the
+//! feature keypair.
+use solana_program::pubkey::Pubkey;
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+
+pub use {
+    instruction::{
+        activate_feature, activate_features, activate_features_at_epoch, activate_features_at_slot,
+        revoke_pending_features,
+    },
+    processor::process,
 };
 
 const FEATURE_GATE_PROGRAM_ID: Pubkey =
@@ -21,14 +36,3 @@ solana_program::declare_id!("CBMTActivator111111111111111111111111111111");
 
 #[cfg(feature = "sbf-entrypoint")]
 solana_program::entrypoint!(process);
-
-pub fn activate_feature(feature_id: &Pubkey) -> Instruction {
-    Instruction::new_with_bytes(crate::id(), &[], vec![AccountMeta::new(*feature_id, false)])
-}
-
-pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], _input: &[u8]) -> ProgramResult {
-    accounts
-        .first()
-        .ok_or(ProgramError::NotEnoughAccountKeys)
-        .map(|info| info.assign(&FEATURE_GATE_PROGRAM_ID))
-}
@@ -0,0 +1,80 @@
+//! Processing of the activator program instructions.
+
+use {
+    crate::{error::ActivatorError, instruction::ActivatorInstruction, FEATURE_GATE_PROGRAM_ID},
+    solana_program::{
+        account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+        program_error::ProgramError, pubkey::Pubkey, sysvar::Sysvar,
+    },
+};
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    match ActivatorInstruction::unpack(input)? {
+        ActivatorInstruction::Activate => (),
+        ActivatorInstruction::ActivateAtSlot { slot } => {
+            if Clock::get()?.slot < slot {
+                return Err(ActivatorError::SlotNotReached.into());
+            }
+        }
+        ActivatorInstruction::ActivateAtEpoch { epoch } => {
+            if Clock::get()?.epoch < epoch {
+                return Err(ActivatorError::EpochNotReached.into());
+            }
+        }
+        ActivatorInstruction::Revoke => return revoke(program_id, accounts),
+    }
+
+    activate(program_id, accounts)
+}
+
+// Assigns every feature account to the Feature Gate program; the runtime
+// activates them at the next epoch boundary.
+fn activate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    for info in accounts {
+        if info.owner != program_id {
+            return Err(ActivatorError::InvalidAccountOwner.into());
+        }
+        if !info.is_writable {
+            return Err(ActivatorError::AccountNotWritable.into());
+        }
+
+        info.assign(&FEATURE_GATE_PROGRAM_ID);
+    }
+
+    Ok(())
+}
+
+// Gives every pending feature account back to the activator program, so that
+// the runtime does not activate them. Pending feature accounts are owned by
+// the Feature Gate program, so only the copy of this program deployed at its
+// id can assign them.
+fn revoke(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    if program_id != &FEATURE_GATE_PROGRAM_ID {
+        return Err(ActivatorError::NotFeatureGate.into());
+    }
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    for info in accounts {
+        if info.owner != program_id {
+            return Err(ActivatorError::InvalidAccountOwner.into());
+        }
+        if !info.is_writable {
+            return Err(ActivatorError::AccountNotWritable.into());
+        }
+        // The runtime sets the activation slot at the epoch boundary; until
+        // then the data is zeroed, which also allows assigning the account.
+        if info.try_borrow_data()?.iter().any(|byte| *byte != 0) {
+            return Err(ActivatorError::FeatureActive.into());
+        }
+
+        info.assign(&crate::id());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Tests of the activator program, running the built ELF.
+//!
+//! The ELF is loaded from `BPF_OUT_DIR` (see `make test`).
+
+use {
+    agave_feature_set::replace_spl_token_with_p_token,
+    cbmt_program_activator::{
+        activate_feature, activate_features, activate_features_at_epoch, activate_features_at_slot,
+        error::ActivatorError, instruction::ActivatorInstruction, revoke_pending_features,
+    },
+    solana_feature_gate_interface::{from_account, Feature},
+    solana_program_test::{ProgramTest, ProgramTestContext},
+    solana_sdk::{
+        account::Account,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        rent::Rent,
+        signer::Signer,
+        transaction::{Transaction, TransactionError},
+    },
+    solana_sdk_ids::{feature, system_program},
+};
+
+/// Feature staged for activation; inactive on the test validator.
+const FEATURE_ID: Pubkey = replace_spl_token_with_p_token::ID;
+
+// Returns a program test with the activator program and the feature
+// account staged. The activator is also deployed at the Feature Gate program
+// id, as on the test validator of the harness.
+fn program_test() -> ProgramTest {
+    let mut program_test =
+        ProgramTest::new("cbmt_program_activator", cbmt_program_activator::id(), None);
+    program_test.prefer_bpf(true);
+    program_test.add_program("cbmt_program_activator", feature::id(), None);
+    program_test.deactivate_feature(FEATURE_ID);
+    program_test.add_account(FEATURE_ID, feature_account(cbmt_program_activator::id()));
+    program_test
+}
+
+fn feature_account(owner: Pubkey) -> Account {
+    let space = Feature::size_of();
+
+    Account {
+        lamports: Rent::default().minimum_balance(space),
+        data: vec![0; space],
+        owner,
+        ..Account::default()
+    }
+}
+
+async fn setup() -> ProgramTestContext {
+    program_test().start_with_context().await
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|error| error.unwrap())
+}
+
+async fn owner(context: &mut ProgramTestContext, address: &Pubkey) -> Pubkey {
+    context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap()
+        .owner
+}
+
+fn custom_error(error: ActivatorError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn activate_assigns_feature_account() {
+    let mut context = setup().await;
+
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}
+
+#[tokio::test]
+async fn activate_with_empty_data() {
+    let mut context = setup().await;
+
+    let instruction = Instruction::new_with_bytes(
+        cbmt_program_activator::id(),
+        &[],
+        vec![AccountMeta::new(FEATURE_ID, false)],
+    );
+
+    process(&mut context, instruction).await.unwrap();
+
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}
+
+#[tokio::test]
+async fn fail_missing_accounts() {
+    let mut context = setup().await;
+
+    let error = process(&mut context, activate_features(&[]))
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys)
+    );
+}
+
+#[tokio::test]
+async fn fail_account_not_writable() {
+    let mut context = setup().await;
+
+    let mut instruction = activate_feature(&FEATURE_ID);
+    instruction.accounts[0].is_writable = false;
+
+    let error = process(&mut context, instruction).await.unwrap_err();
+
+    assert_eq!(error, custom_error(ActivatorError::AccountNotWritable));
+}
+
+#[tokio::test]
+async fn fail_account_not_owned_by_activator() {
+    let other = Pubkey::new_unique();
+
+    let mut program_test = program_test();
+    program_test.add_account(other, feature_account(system_program::id()));
+    let mut context = program_test.start_with_context().await;
+
+    // The whole instruction fails, including the staged feature account.
+    let error = process(&mut context, activate_features(&[FEATURE_ID, other]))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, custom_error(ActivatorError::InvalidAccountOwner));
+    assert_eq!(
+        owner(&mut context, &FEATURE_ID).await,
+        cbmt_program_activator::id()
+    );
+}
+
+#[tokio::test]
+async fn activate_at_slot_and_epoch() {
+    let mut context = setup().await;
+
+    let error = process(
+        &mut context,
+        activate_features_at_slot(&[FEATURE_ID], u64::MAX),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, custom_error(ActivatorError::SlotNotReached));
+
+    let error = process(
+        &mut context,
+        activate_features_at_epoch(&[FEATURE_ID], u64::MAX),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, custom_error(ActivatorError::EpochNotReached));
+
+    process(&mut context, activate_features_at_epoch(&[FEATURE_ID], 0))
+        .await
+        .unwrap();
+
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}
+
+#[tokio::test]
+async fn feature_activated_at_epoch_boundary() {
+    let mut context = setup().await;
+
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+
+    // Pending until the next epoch.
+    let account = context
+        .banks_client
+        .get_account(FEATURE_ID)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(from_account(&account).unwrap().activated_at, None);
+
+    let epoch_schedule = context.genesis_config().epoch_schedule.clone();
+    let epoch = epoch_schedule.get_epoch(context.banks_client.get_root_slot().await.unwrap());
+    let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch + 1);
+
+    context.warp_to_epoch(epoch + 1).unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(FEATURE_ID)
+        .await
+        .unwrap()
+        .unwrap();
+    let activated_at = from_account(&account).unwrap().activated_at.unwrap();
+
+    assert_eq!(epoch_schedule.get_epoch(activated_at), epoch + 1);
+    assert!(activated_at >= first_slot);
+}
+
+#[tokio::test]
+async fn revoke_returns_feature_account() {
+    let mut context = setup().await;
+
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+    process(&mut context, revoke_pending_features(&[FEATURE_ID]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        owner(&mut context, &FEATURE_ID).await,
+        cbmt_program_activator::id()
+    );
+
+    // The feature can be activated again, in a new transaction.
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}
+
+#[tokio::test]
+async fn fail_revoke_not_feature_gate() {
+    let mut context = setup().await;
+
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+
+    let instruction = Instruction::new_with_bytes(
+        cbmt_program_activator::id(),
+        &ActivatorInstruction::Revoke.pack(),
+        vec![AccountMeta::new(FEATURE_ID, false)],
+    );
+
+    let error = process(&mut context, instruction).await.unwrap_err();
+
+    assert_eq!(error, custom_error(ActivatorError::NotFeatureGate));
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}
+
+#[tokio::test]
+async fn fail_revoke_staged_feature() {
+    let mut context = setup().await;
+
+    let error = process(&mut context, revoke_pending_features(&[FEATURE_ID]))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, custom_error(ActivatorError::InvalidAccountOwner));
+}
+
+#[tokio::test]
+async fn fail_revoke_activated_feature() {
+    let mut context = setup().await;
+
+    process(&mut context, activate_feature(&FEATURE_ID))
+        .await
+        .unwrap();
+
+    let epoch_schedule = context.genesis_config().epoch_schedule.clone();
+    let epoch = epoch_schedule.get_epoch(context.banks_client.get_root_slot().await.unwrap());
+    context.warp_to_epoch(epoch + 1).unwrap();
+    context.last_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    let error = process(&mut context, revoke_pending_features(&[FEATURE_ID]))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error, custom_error(ActivatorError::FeatureActive));
+    assert_eq!(owner(&mut context, &FEATURE_ID).await, feature::id());
+}